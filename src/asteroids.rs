@@ -1,45 +1,152 @@
-use bevy::{math::vec3, prelude::*, window::PrimaryWindow};
+use bevy::{math::vec3, prelude::*, utils::HashSet, window::PrimaryWindow};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-pub const ASTEROID_SIZE: f32 = 50.;
+use crate::{
+    AsteroidConfig, BulletHitAsteroid, Collider, CollisionLayers, GameAssets, GameConfig, InGame,
+    PlayerHitAsteroid, SizeMix,
+};
 
-#[derive(Component)]
-pub struct Asteroid;
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AsteroidSize {
+    Large,
+    Medium,
+    Small,
+}
+
+impl AsteroidSize {
+    pub const ALL: [AsteroidSize; 3] = [
+        AsteroidSize::Large,
+        AsteroidSize::Medium,
+        AsteroidSize::Small,
+    ];
+
+    pub fn smaller(self) -> Option<AsteroidSize> {
+        match self {
+            AsteroidSize::Large => Some(AsteroidSize::Medium),
+            AsteroidSize::Medium => Some(AsteroidSize::Small),
+            AsteroidSize::Small => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AsteroidSize::Large => "large",
+            AsteroidSize::Medium => "medium",
+            AsteroidSize::Small => "small",
+        }
+    }
+}
 
 #[derive(Component)]
+pub struct Asteroid {
+    pub size: AsteroidSize,
+    pub hit_points: u32,
+}
+
+#[derive(Component, Clone, Copy)]
 pub struct Velocity {
     pub speed: f32,
     pub direction_x: f32,
 }
 
-pub fn spawn_asteroids(commands: &mut Commands, texture: Handle<Image>, win: &Window) {
-    let mut rng = rand::thread_rng();
-    let y = win.height();
-
-    for _ in 0..20 {
-        let x = rng.gen_range(-win.width() / 2.0 + 20.0..win.width() / 2.0 - 20.0);
-        let falling_speed = rng.gen_range(150.0..400.0);
-        let falling_x = rng.gen_range(-40.0..40.0);
-
-        commands.spawn((
-            SpriteBundle {
-                transform: Transform {
-                    translation: vec3(x, y, -1.0),
-                    ..default()
-                },
-                sprite: Sprite {
-                    custom_size: Some(Vec2::splat(ASTEROID_SIZE)),
-                    ..default()
-                },
-                texture: texture.clone(),
+#[derive(Event, Clone, Copy)]
+pub struct AsteroidDestroyed {
+    pub asteroid: Entity,
+    pub size: AsteroidSize,
+    pub translation: Vec3,
+    pub velocity: Velocity,
+    /// Player whose shot broke the asteroid.
+    pub destroyed_by: Option<Entity>,
+}
+
+fn asteroid_collider(size: f32) -> Collider {
+    Collider::Circle { radius: size / 2. }
+}
+
+pub fn spawn_asteroid(
+    commands: &mut Commands,
+    config: &AsteroidConfig,
+    size: AsteroidSize,
+    texture: Handle<Image>,
+    translation: Vec3,
+    velocity: Velocity,
+) {
+    let size_config = config.get(size);
+    commands.spawn((
+        SpriteBundle {
+            transform: Transform {
+                translation,
                 ..default()
             },
-            Asteroid,
-            Velocity {
-                speed: falling_speed,
-                direction_x: falling_x,
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(size_config.size)),
+                ..default()
             },
-        ));
+            texture,
+            ..default()
+        },
+        Asteroid {
+            size,
+            hit_points: size_config.hit_points,
+        },
+        velocity,
+        asteroid_collider(size_config.size),
+        CollisionLayers::new(
+            CollisionLayers::ASTEROID,
+            CollisionLayers::PLAYER | CollisionLayers::BULLET,
+        ),
+        StateScoped(InGame),
+    ));
+}
+
+pub fn pick_size(mix: &SizeMix, rng: &mut impl Rng) -> AsteroidSize {
+    let mut roll = rng.gen_range(0..mix.total().max(1));
+    for size in AsteroidSize::ALL {
+        let weight = mix.weight(size);
+        if roll < weight {
+            return size;
+        }
+        roll -= weight;
+    }
+    AsteroidSize::Medium
+}
+
+/// Spawns an asteroid just above the screen at a random x.
+pub fn spawn_falling_asteroid(
+    commands: &mut Commands,
+    config: &AsteroidConfig,
+    size: AsteroidSize,
+    texture: Handle<Image>,
+    win: &Window,
+    velocity: Velocity,
+    rng: &mut impl Rng,
+) {
+    let half_width = (win.width() / 2. - config.get(size).size / 2.).max(1.);
+    let x = rng.gen_range(-half_width..half_width);
+    spawn_asteroid(
+        commands,
+        config,
+        size,
+        texture,
+        vec3(x, win.height() / 2. + 50., -1.0),
+        velocity,
+    );
+}
+
+pub fn apply_asteroid_config(
+    config: Res<GameConfig>,
+    mut query: Query<(&mut Asteroid, &mut Sprite, &mut Collider, &mut Velocity)>,
+) {
+    let config = &config.asteroids;
+    for (mut asteroid, mut sprite, mut collider, mut velocity) in query.iter_mut() {
+        let size_config = config.get(asteroid.size);
+        sprite.custom_size = Some(Vec2::splat(size_config.size));
+        *collider = asteroid_collider(size_config.size);
+        asteroid.hit_points = asteroid.hit_points.min(size_config.hit_points);
+        velocity.direction_x = velocity
+            .direction_x
+            .clamp(-config.max_drift, config.max_drift);
     }
 }
 
@@ -54,18 +161,91 @@ pub fn update_asteroids(
         transform.translation.x += velocity.direction_x * dt;
     }
 }
-pub fn replace_asteroids(
-    mut query: Query<(&mut Transform, &Velocity), With<Asteroid>>,
+pub fn despawn_escaped_asteroids(
+    mut commands: Commands,
+    query: Query<(Entity, &Transform, &Asteroid)>,
     win_query: Query<&Window, With<PrimaryWindow>>,
+    config: Res<GameConfig>,
 ) {
     if let Ok(win) = win_query.get_single() {
-        for (mut transform, _) in query.iter_mut() {
-            if transform.translation.y < -win.height() / 2. {
-                let mut rng = rand::thread_rng();
-                let x = rng.gen_range(-win.width() + 5. / 2.0..win.width() / 2. - 5.);
-                transform.translation.y = win.height() / 2.;
-                transform.translation.x = x;
+        for (entity, transform, asteroid) in query.iter() {
+            let size = config.asteroids.get(asteroid.size).size;
+            if transform.translation.y < -win.height() / 2. - size {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+pub fn damage_asteroids(
+    mut hits: EventReader<BulletHitAsteroid>,
+    mut query: Query<(&Transform, &Velocity, &mut Asteroid)>,
+    mut destroyed: EventWriter<AsteroidDestroyed>,
+) {
+    for hit in hits.read() {
+        if let Ok((transform, velocity, mut asteroid)) = query.get_mut(hit.asteroid) {
+            asteroid.hit_points = asteroid.hit_points.saturating_sub(hit.damage);
+            if asteroid.hit_points == 0 {
+                destroyed.send(AsteroidDestroyed {
+                    asteroid: hit.asteroid,
+                    size: asteroid.size,
+                    translation: transform.translation,
+                    velocity: *velocity,
+                    destroyed_by: hit.owner,
+                });
             }
         }
     }
 }
+
+/// Breaks destroyed asteroids into fragments of the next size down, fanned out sideways.
+pub fn split_asteroids(
+    mut commands: Commands,
+    mut destroyed: EventReader<AsteroidDestroyed>,
+    config: Res<GameConfig>,
+    game_assets: Res<GameAssets>,
+) {
+    let config = &config.asteroids;
+    for event in destroyed.read() {
+        let Some(fragment_size) = event.size.smaller() else {
+            continue;
+        };
+        let count = config.get(event.size).fragments;
+        let fragment_width = config.get(fragment_size).size;
+        for i in 0..count {
+            let spread = if count > 1 {
+                i as f32 / (count - 1) as f32 * 2. - 1.
+            } else {
+                0.
+            };
+            let velocity = Velocity {
+                speed: event.velocity.speed,
+                direction_x: event.velocity.direction_x + spread * config.fragment_spread,
+            };
+            let translation = event.translation + vec3(spread * fragment_width / 2., 0., 0.);
+            spawn_asteroid(
+                &mut commands,
+                config,
+                fragment_size,
+                game_assets.asteroid.clone(),
+                translation,
+                velocity,
+            );
+        }
+    }
+}
+
+pub fn despawn_hit_asteroids(
+    mut commands: Commands,
+    mut destroyed: EventReader<AsteroidDestroyed>,
+    mut player_hits: EventReader<PlayerHitAsteroid>,
+) {
+    let hit_asteroids: HashSet<Entity> = destroyed
+        .read()
+        .map(|event| event.asteroid)
+        .chain(player_hits.read().map(|hit| hit.asteroid))
+        .collect();
+    for asteroid in hit_asteroids {
+        commands.entity(asteroid).despawn();
+    }
+}
@@ -1,5 +1,4 @@
 use std::time::Duration;
-const STAR_FALLING_SPEED: f32 = 150.0;
 
 use bevy::{
     math::{vec2, vec3},
@@ -8,6 +7,8 @@ use bevy::{
 };
 use rand::Rng;
 
+use crate::{BackgroundRng, GameAssets, GameConfig};
+
 #[derive(Component)]
 pub struct Star;
 
@@ -55,13 +56,13 @@ pub fn update_stars(
     mut query: Query<&mut Transform, With<Star>>,
     events: EventReader<WindowResized>,
     win_query: Query<&Window, With<PrimaryWindow>>,
+    mut rng: ResMut<BackgroundRng>,
 ) {
     if !events.is_empty() {
         if let Ok(win) = win_query.get_single() {
-            let mut rng = rand::thread_rng();
             for mut star in query.iter_mut() {
-                let x = rng.gen_range((-1. * (win.width() / 2.))..win.width() / 2.);
-                let y = rng.gen_range((-1. * (win.height() / 2.))..win.height() / 2.);
+                let x = rng.0.gen_range(-(win.width() / 2.)..win.width() / 2.);
+                let y = rng.0.gen_range(-(win.height() / 2.)..win.height() / 2.);
                 star.translation.x = x;
                 star.translation.y = y;
             }
@@ -104,14 +105,15 @@ pub fn spawn_background(commands: &mut Commands, win: &Window) {
 pub fn spawn_stars(
     commands: &mut Commands,
     win: &Window,
+    count: usize,
     texture: &Handle<Image>,
     texture_atlas_layout: &Handle<TextureAtlasLayout>,
     animation_config: &AnimationConfig,
+    rng: &mut impl Rng,
 ) {
-    let mut rng = rand::thread_rng();
-    for _ in 0..500 {
-        let x = rng.gen_range((-1. * (win.width() / 2.))..win.width() / 2.);
-        let y = rng.gen_range((-1. * (win.height() / 2.))..win.height() / 2.);
+    for _ in 0..count {
+        let x = rng.gen_range(-(win.width() / 2.)..win.width() / 2.);
+        let y = rng.gen_range(-(win.height() / 2.)..win.height() / 2.);
         let animation_config = animation_config.clone();
         commands.spawn((
             SpriteBundle {
@@ -136,14 +138,44 @@ pub fn spawn_stars(
     }
 }
 
+/// Spawns or despawns stars until their number matches the config, so the
+/// starfield follows `stars.count` when the config is (re)loaded.
+pub fn sync_star_count(
+    mut commands: Commands,
+    stars_query: Query<Entity, With<Star>>,
+    win_query: Query<&Window, With<PrimaryWindow>>,
+    game_assets: Res<GameAssets>,
+    config: Res<GameConfig>,
+    mut rng: ResMut<BackgroundRng>,
+) {
+    let current = stars_query.iter().count();
+    let wanted = config.stars.count;
+    if wanted < current {
+        for star in stars_query.iter().skip(wanted) {
+            commands.entity(star).despawn();
+        }
+    } else if let Ok(win) = win_query.get_single() {
+        spawn_stars(
+            &mut commands,
+            win,
+            wanted - current,
+            &game_assets.star,
+            &game_assets.star_layout,
+            &AnimationConfig::new(0, 2, 5),
+            &mut rng.0,
+        );
+    }
+}
+
 pub fn falling_stars(
     mut query: Query<&mut Transform, With<Star>>,
     win_query: Query<&Window, With<PrimaryWindow>>,
+    config: Res<GameConfig>,
     time: Res<Time>,
 ) {
     if let Ok(win) = win_query.get_single() {
         for mut transform in query.iter_mut() {
-            transform.translation.y -= time.delta_seconds() * STAR_FALLING_SPEED;
+            transform.translation.y -= time.delta_seconds() * config.stars.falling_speed;
 
             if transform.translation.y < -win.height() / 2. {
                 transform.translation.y = win.height() / 2.;
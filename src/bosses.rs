@@ -0,0 +1,509 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadedFolder},
+    math::vec3,
+    prelude::*,
+    window::PrimaryWindow,
+};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    asteroids::{spawn_asteroid, AsteroidSize, Velocity},
+    nearest_player, spawn_bullet, ActivePlayer, Bullet, BulletHitEnemy, Collider, CollisionLayers,
+    Faction, GameAssets, GameConfig, GameRng, InGame, Invulnerable, Player, PlayerHitEnemy,
+    WaveEvent,
+};
+
+/// Fraction of the window height, from the top, where the boss settles.
+const HOLD_HEIGHT: f32 = 0.25;
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum AttackPattern {
+    /// Fan of bullets aimed at the player.
+    Spread { count: usize, arc: f32, speed: f32 },
+    /// Full circle of bullets, turned by `spin` degrees on every volley.
+    Ring { count: usize, speed: f32, spin: f32 },
+    /// Drops asteroids from the boss's position.
+    Summon {
+        count: usize,
+        size: AsteroidSize,
+        speed: f32,
+    },
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Attack {
+    /// Seconds between two uses of the pattern.
+    pub every: f32,
+    pub pattern: AttackPattern,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct BossPhase {
+    /// The phase lasts until health drops to this fraction of the maximum.
+    pub until: f32,
+    #[serde(default = "default_speed_multiplier")]
+    pub speed_multiplier: f32,
+    #[serde(default)]
+    pub enraged: bool,
+    pub attacks: Vec<Attack>,
+}
+
+fn default_speed_multiplier() -> f32 {
+    1.
+}
+
+/// A boss read from `assets/bosses/*.boss.ron`.
+#[derive(Asset, TypePath, Deserialize, Clone, Debug)]
+pub struct BossDefinition {
+    pub name: String,
+    pub health: u32,
+    pub size: f32,
+    pub speed: f32,
+    pub score: i64,
+    pub phases: Vec<BossPhase>,
+}
+
+impl BossDefinition {
+    /// Index of the phase matching the remaining fraction of health.
+    pub fn phase_index(&self, health_fraction: f32) -> usize {
+        self.phases
+            .iter()
+            .position(|phase| health_fraction > phase.until)
+            .unwrap_or(self.phases.len().saturating_sub(1))
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if self.health == 0 {
+            errors.push("health must be at least 1".to_string());
+        }
+        if !(self.size > 0. && self.speed > 0.) {
+            errors.push("size and speed must be positive".to_string());
+        }
+        if self.phases.is_empty() {
+            errors.push("at least one phase is needed".to_string());
+        }
+        for (i, phase) in self.phases.iter().enumerate() {
+            if !(0. ..=1.).contains(&phase.until) {
+                errors.push(format!(
+                    "phases[{i}].until must be between 0 and 1, got {}",
+                    phase.until
+                ));
+            }
+            if let Some(previous) = i.checked_sub(1).map(|j| &self.phases[j]) {
+                if phase.until >= previous.until {
+                    errors.push(format!(
+                        "phases[{i}].until ({}) must be less than phases[{}].until ({})",
+                        phase.until,
+                        i - 1,
+                        previous.until
+                    ));
+                }
+            }
+            for attack in &phase.attacks {
+                if attack.every.is_nan() || attack.every <= 0. {
+                    errors.push(format!(
+                        "phases[{i}] has an attack with a non-positive `every`"
+                    ));
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct BossDefinitionLoader;
+
+impl AssetLoader for BossDefinitionLoader {
+    type Asset = BossDefinition;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<BossDefinition, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let definition: BossDefinition = ron::de::from_bytes(&bytes)?;
+        definition.validate().map_err(|errors| errors.join(", "))?;
+        Ok(definition)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["boss.ron"]
+    }
+}
+
+#[derive(Component)]
+pub struct Boss {
+    pub definition: Handle<BossDefinition>,
+    pub health: u32,
+    pub max_health: u32,
+    pub phase: usize,
+    attack_timers: Vec<Timer>,
+    direction: f32,
+    ring_angle: f32,
+}
+
+impl Boss {
+    pub fn health_fraction(&self) -> f32 {
+        self.health as f32 / self.max_health as f32
+    }
+
+    fn enter_phase(&mut self, index: usize, definition: &BossDefinition) {
+        self.phase = index;
+        self.attack_timers = definition.phases[index]
+            .attacks
+            .iter()
+            .map(|attack| Timer::from_seconds(attack.every, TimerMode::Repeating))
+            .collect();
+    }
+}
+
+#[derive(Event, Clone, Copy)]
+pub struct BossDefeated {
+    pub translation: Vec3,
+    pub size: f32,
+    pub score: i64,
+    pub destroyed_by: Option<Entity>,
+}
+
+/// Bosses in file name order, skipping files that failed to load.
+fn boss_definitions(
+    game_assets: &GameAssets,
+    folders: &Assets<LoadedFolder>,
+    definitions: &Assets<BossDefinition>,
+) -> Vec<Handle<BossDefinition>> {
+    let Some(folder) = folders.get(&game_assets.bosses) else {
+        return Vec::new();
+    };
+    let mut handles: Vec<_> = folder
+        .handles
+        .iter()
+        .filter_map(|handle| handle.clone().try_typed::<BossDefinition>().ok())
+        .filter(|handle| definitions.contains(handle))
+        .collect();
+    handles.sort_by_key(|handle| handle.path().map(|path| path.to_string()));
+    handles
+}
+
+pub fn spawn_bosses(
+    mut commands: Commands,
+    mut wave_events: EventReader<WaveEvent>,
+    win_query: Query<&Window, With<PrimaryWindow>>,
+    game_assets: Res<GameAssets>,
+    folders: Res<Assets<LoadedFolder>>,
+    definitions: Res<Assets<BossDefinition>>,
+    config: Res<GameConfig>,
+) {
+    let Ok(win) = win_query.get_single() else {
+        return;
+    };
+    for event in wave_events.read() {
+        let WaveEvent::Started(wave) = *event else {
+            continue;
+        };
+        if !config.waves.is_boss_wave(wave) {
+            continue;
+        }
+        let handles = boss_definitions(&game_assets, &folders, &definitions);
+        if handles.is_empty() {
+            warn!("wave {wave} should have a boss but no boss definitions are loaded");
+            continue;
+        }
+        let index = (wave / config.waves.boss_every - 1) as usize % handles.len();
+        let handle = handles[index].clone();
+        let Some(definition) = definitions.get(&handle) else {
+            continue;
+        };
+        let mut boss = Boss {
+            definition: handle,
+            health: definition.health,
+            max_health: definition.health,
+            phase: 0,
+            attack_timers: Vec::new(),
+            direction: 1.,
+            ring_angle: 0.,
+        };
+        boss.enter_phase(0, definition);
+        info!("boss {} arrives on wave {wave}", definition.name);
+        commands.spawn((
+            SpriteBundle {
+                transform: Transform {
+                    translation: vec3(0., win.height() / 2. + definition.size, 0.),
+                    rotation: Quat::from_rotation_z(PI),
+                    ..default()
+                },
+                sprite: Sprite {
+                    color: Color::srgb(0.7, 0.4, 1.),
+                    custom_size: Some(Vec2::splat(definition.size)),
+                    ..default()
+                },
+                texture: game_assets.space_ship.clone(),
+                ..default()
+            },
+            boss,
+            Collider::Aabb {
+                half_extents: Vec2::splat(definition.size * 0.45),
+            },
+            CollisionLayers::new(
+                CollisionLayers::ENEMY,
+                CollisionLayers::PLAYER | CollisionLayers::BULLET,
+            ),
+            StateScoped(InGame),
+        ));
+    }
+}
+
+pub fn move_bosses(
+    mut boss_query: Query<(&mut Boss, &mut Transform)>,
+    win_query: Query<&Window, With<PrimaryWindow>>,
+    definitions: Res<Assets<BossDefinition>>,
+    time: Res<Time>,
+) {
+    let Ok(win) = win_query.get_single() else {
+        return;
+    };
+    let dt = time.delta_seconds();
+    let hold_y = win.height() / 2. - win.height() * HOLD_HEIGHT;
+    for (mut boss, mut transform) in boss_query.iter_mut() {
+        let Some(definition) = definitions.get(&boss.definition) else {
+            continue;
+        };
+        let phase = &definition.phases[boss.phase.min(definition.phases.len() - 1)];
+        let speed = definition.speed * phase.speed_multiplier;
+        if transform.translation.y > hold_y {
+            transform.translation.y -= speed * dt;
+            continue;
+        }
+        transform.translation.x += boss.direction * speed * dt;
+        let edge = (win.width() / 2. - definition.size / 2.).max(0.);
+        if transform.translation.x.abs() > edge {
+            transform.translation.x = transform.translation.x.clamp(-edge, edge);
+            boss.direction = -boss.direction;
+        }
+    }
+}
+
+/// Switches phases as health drops and runs the attacks of the current phase.
+#[allow(clippy::too_many_arguments)]
+pub fn boss_attacks(
+    mut commands: Commands,
+    mut boss_query: Query<(&mut Boss, &Transform, &mut Sprite)>,
+    player_query: Query<&Transform, ActivePlayer>,
+    win_query: Query<&Window, With<PrimaryWindow>>,
+    definitions: Res<Assets<BossDefinition>>,
+    game_assets: Res<GameAssets>,
+    assets: Res<AssetServer>,
+    config: Res<GameConfig>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    let Ok(win) = win_query.get_single() else {
+        return;
+    };
+    for (mut boss, transform, mut sprite) in boss_query.iter_mut() {
+        let Some(definition) = definitions.get(&boss.definition) else {
+            continue;
+        };
+        let phase_index = definition.phase_index(boss.health_fraction());
+        // A hot-reloaded definition can change the number of attacks.
+        if phase_index != boss.phase
+            || boss.attack_timers.len() != definition.phases[phase_index].attacks.len()
+        {
+            boss.enter_phase(phase_index, definition);
+            let phase = &definition.phases[phase_index];
+            if phase.enraged {
+                info!("boss {} is enraged", definition.name);
+            }
+            sprite.color = if phase.enraged {
+                Color::srgb(1., 0.2, 0.2)
+            } else {
+                Color::srgb(0.7, 0.4, 1.)
+            };
+        }
+        // No attacks until the boss is on screen.
+        let position = transform.translation;
+        if position.y > win.height() / 2. {
+            continue;
+        }
+        let player = nearest_player(&player_query, position.truncate());
+        let phase = &definition.phases[boss.phase];
+        for (i, attack) in phase.attacks.iter().enumerate() {
+            if !boss.attack_timers[i].tick(time.delta()).just_finished() {
+                continue;
+            }
+            match &attack.pattern {
+                AttackPattern::Spread { count, arc, speed } => {
+                    let aim = player
+                        .map(|player| player - position.truncate())
+                        .and_then(|aim| aim.try_normalize())
+                        .unwrap_or(Vec2::NEG_Y);
+                    let step = if *count > 1 {
+                        arc.to_radians() / (*count - 1) as f32
+                    } else {
+                        0.
+                    };
+                    let first = -step * (*count as f32 - 1.) / 2.;
+                    for n in 0..*count {
+                        let direction = Vec2::from_angle(first + step * n as f32).rotate(aim);
+                        spawn_bullet(
+                            &mut commands,
+                            Bullet::new(Faction::Enemy, direction * *speed),
+                            assets.load("Spritesheet/fire.png"),
+                            position.with_z(-1.),
+                            Vec2::splat(config.bullet.size),
+                        );
+                    }
+                }
+                AttackPattern::Ring { count, speed, spin } => {
+                    boss.ring_angle += spin.to_radians();
+                    for n in 0..*count {
+                        let angle = boss.ring_angle + 2. * PI * n as f32 / *count as f32;
+                        spawn_bullet(
+                            &mut commands,
+                            Bullet::new(Faction::Enemy, Vec2::from_angle(angle) * *speed),
+                            assets.load("Spritesheet/fire.png"),
+                            position.with_z(-1.),
+                            Vec2::splat(config.bullet.size),
+                        );
+                    }
+                }
+                AttackPattern::Summon { count, size, speed } => {
+                    let max_drift = config.asteroids.max_drift;
+                    for _ in 0..*count {
+                        let velocity = Velocity {
+                            speed: *speed,
+                            direction_x: rng.gen_range(-max_drift..max_drift) * 3.,
+                        };
+                        spawn_asteroid(
+                            &mut commands,
+                            &config.asteroids,
+                            *size,
+                            game_assets.asteroid.clone(),
+                            position.with_z(-1.),
+                            velocity,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Bullets and rams wear a boss down. A ram costs the boss as much as it costs the ship, so
+/// it only counts while the ship isn't invulnerable from an earlier hit.
+#[allow(clippy::too_many_arguments)]
+pub fn damage_bosses(
+    mut commands: Commands,
+    mut bullet_hits: EventReader<BulletHitEnemy>,
+    mut rams: EventReader<PlayerHitEnemy>,
+    mut boss_query: Query<(&mut Boss, &Transform)>,
+    player_query: Query<(), (With<Player>, Without<Invulnerable>)>,
+    definitions: Res<Assets<BossDefinition>>,
+    config: Res<GameConfig>,
+    mut defeated: EventWriter<BossDefeated>,
+) {
+    let hits = bullet_hits
+        .read()
+        .map(|hit| (hit.enemy, hit.damage, hit.owner))
+        .chain(
+            rams.read()
+                .filter(|ram| player_query.contains(ram.player))
+                .map(|ram| (ram.enemy, config.player.collision_damage, Some(ram.player))),
+        )
+        .collect::<Vec<_>>();
+    for (enemy, damage, owner) in hits {
+        let Ok((mut boss, transform)) = boss_query.get_mut(enemy) else {
+            continue;
+        };
+        if boss.health == 0 {
+            continue;
+        }
+        boss.health = boss.health.saturating_sub(damage);
+        if boss.health == 0 {
+            let (size, score) = definitions
+                .get(&boss.definition)
+                .map(|definition| (definition.size, definition.score))
+                .unwrap_or_default();
+            defeated.send(BossDefeated {
+                translation: transform.translation,
+                size,
+                score,
+                destroyed_by: owner,
+            });
+            commands.entity(enemy).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(phases: &[f32]) -> BossDefinition {
+        BossDefinition {
+            name: "Test".to_string(),
+            health: 10,
+            size: 100.,
+            speed: 50.,
+            score: 100,
+            phases: phases
+                .iter()
+                .map(|&until| BossPhase {
+                    until,
+                    speed_multiplier: 1.,
+                    enraged: false,
+                    attacks: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn phase_follows_remaining_health() {
+        let boss = definition(&[0.6, 0.25, 0.]);
+        assert_eq!(boss.phase_index(1.), 0);
+        assert_eq!(boss.phase_index(0.61), 0);
+        assert_eq!(boss.phase_index(0.6), 1);
+        assert_eq!(boss.phase_index(0.25), 2);
+        assert_eq!(boss.phase_index(0.), 2);
+    }
+
+    #[test]
+    fn shipped_bosses_are_valid() {
+        for file in [
+            include_str!("../assets/bosses/mothership.boss.ron"),
+            include_str!("../assets/bosses/warden.boss.ron"),
+        ] {
+            let boss: BossDefinition = ron::from_str(file).unwrap();
+            assert_eq!(boss.validate(), Ok(()), "{}", boss.name);
+        }
+    }
+
+    #[test]
+    fn phases_must_step_down_within_zero_and_one() {
+        assert_eq!(definition(&[0.5, 0.]).validate(), Ok(()));
+        let errors = definition(&[0.5, 0.5]).validate().unwrap_err();
+        assert!(errors[0].starts_with("phases[1].until (0.5) must be less than"));
+        let errors = definition(&[1.5, -0.1]).validate().unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                "phases[0].until must be between 0 and 1, got 1.5",
+                "phases[1].until must be between 0 and 1, got -0.1",
+            ]
+        );
+    }
+}
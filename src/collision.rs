@@ -0,0 +1,382 @@
+use bevy::{prelude::*, utils::HashMap};
+
+const GRID_CELL_SIZE: f32 = 100.;
+
+#[derive(Component, Clone, Debug)]
+pub enum Collider {
+    Circle {
+        radius: f32,
+    },
+    Aabb {
+        half_extents: Vec2,
+    },
+    /// Points in local space, in counter-clockwise or clockwise order.
+    ConvexPolygon {
+        points: Vec<Vec2>,
+    },
+}
+
+/// Which groups an entity belongs to and which groups it reacts to. Both sides have
+/// to accept each other for a pair to be reported. Entities without it hit everything.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CollisionLayers {
+    pub memberships: u32,
+    pub filters: u32,
+}
+
+impl CollisionLayers {
+    pub const PLAYER: u32 = 1 << 0;
+    pub const BULLET: u32 = 1 << 1;
+    pub const ASTEROID: u32 = 1 << 2;
+    pub const ENEMY: u32 = 1 << 3;
+    pub const ENEMY_BULLET: u32 = 1 << 4;
+    pub const PICKUP: u32 = 1 << 5;
+    pub const ALL: u32 = u32::MAX;
+
+    pub fn new(memberships: u32, filters: u32) -> Self {
+        Self {
+            memberships,
+            filters,
+        }
+    }
+
+    fn interacts(&self, other: &CollisionLayers) -> bool {
+        self.filters & other.memberships != 0 && other.filters & self.memberships != 0
+    }
+}
+
+impl Default for CollisionLayers {
+    fn default() -> Self {
+        Self::new(Self::ALL, Self::ALL)
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Collision(pub Entity, pub Entity);
+
+impl Collision {
+    /// Returns both entities with the one matching `first` in front, if either does.
+    pub fn ordered_by(&self, first: impl Fn(Entity) -> bool) -> Option<(Entity, Entity)> {
+        if first(self.0) {
+            Some((self.0, self.1))
+        } else if first(self.1) {
+            Some((self.1, self.0))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BulletHitAsteroid {
+    pub bullet: Entity,
+    pub asteroid: Entity,
+    pub damage: u32,
+    pub owner: Option<Entity>,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlayerHitAsteroid {
+    pub player: Entity,
+    pub asteroid: Entity,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BulletHitEnemy {
+    pub bullet: Entity,
+    pub enemy: Entity,
+    pub damage: u32,
+    pub owner: Option<Entity>,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BulletHitPlayer {
+    pub bullet: Entity,
+    pub player: Entity,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlayerHitEnemy {
+    pub player: Entity,
+    pub enemy: Entity,
+}
+
+enum WorldShape {
+    Circle { center: Vec2, radius: f32 },
+    Polygon(Vec<Vec2>),
+}
+
+impl Collider {
+    pub fn bounding_radius(&self, transform: &Transform) -> f32 {
+        let scale = transform.scale.x.abs().max(transform.scale.y.abs());
+        let radius = match self {
+            Collider::Circle { radius } => *radius,
+            Collider::Aabb { half_extents } => half_extents.length(),
+            Collider::ConvexPolygon { points } => {
+                points.iter().map(|p| p.length()).fold(0., f32::max)
+            }
+        };
+        radius * scale
+    }
+
+    fn world_shape(&self, transform: &Transform) -> WorldShape {
+        let center = transform.translation.truncate();
+        match self {
+            Collider::Circle { radius } => WorldShape::Circle {
+                center,
+                radius: radius * transform.scale.x.abs().max(transform.scale.y.abs()),
+            },
+            // Axis aligned on purpose, rotation is ignored.
+            Collider::Aabb { half_extents } => {
+                let half = *half_extents * transform.scale.truncate().abs();
+                WorldShape::Polygon(vec![
+                    center + Vec2::new(-half.x, -half.y),
+                    center + Vec2::new(half.x, -half.y),
+                    center + Vec2::new(half.x, half.y),
+                    center + Vec2::new(-half.x, half.y),
+                ])
+            }
+            Collider::ConvexPolygon { points } => WorldShape::Polygon(
+                points
+                    .iter()
+                    .map(|p| transform.transform_point(p.extend(0.)).truncate())
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn intersects(
+        &self,
+        transform: &Transform,
+        other: &Collider,
+        other_transform: &Transform,
+    ) -> bool {
+        match (
+            self.world_shape(transform),
+            other.world_shape(other_transform),
+        ) {
+            (
+                WorldShape::Circle { center, radius },
+                WorldShape::Circle {
+                    center: other_center,
+                    radius: other_radius,
+                },
+            ) => center.distance_squared(other_center) < (radius + other_radius).powi(2),
+            (WorldShape::Circle { center, radius }, WorldShape::Polygon(polygon))
+            | (WorldShape::Polygon(polygon), WorldShape::Circle { center, radius }) => {
+                circle_polygon_overlap(center, radius, &polygon)
+            }
+            (WorldShape::Polygon(a), WorldShape::Polygon(b)) => polygons_overlap(&a, &b),
+        }
+    }
+}
+
+fn project(points: &[Vec2], axis: Vec2) -> (f32, f32) {
+    points
+        .iter()
+        .map(|p| p.dot(axis))
+        .fold((f32::MAX, f32::MIN), |(min, max), d| {
+            (min.min(d), max.max(d))
+        })
+}
+
+fn edge_normals(points: &[Vec2]) -> impl Iterator<Item = Vec2> + '_ {
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .filter_map(|(a, b)| (*b - *a).perp().try_normalize())
+}
+
+/// Separating axis test over the edge normals of both polygons.
+fn polygons_overlap(a: &[Vec2], b: &[Vec2]) -> bool {
+    edge_normals(a).chain(edge_normals(b)).all(|axis| {
+        let (a_min, a_max) = project(a, axis);
+        let (b_min, b_max) = project(b, axis);
+        a_max > b_min && b_max > a_min
+    })
+}
+
+fn circle_polygon_overlap(center: Vec2, radius: f32, polygon: &[Vec2]) -> bool {
+    let closest_vertex = polygon
+        .iter()
+        .min_by(|a, b| {
+            a.distance_squared(center)
+                .total_cmp(&b.distance_squared(center))
+        })
+        .copied()
+        .unwrap_or(center);
+    edge_normals(polygon)
+        .chain((closest_vertex - center).try_normalize())
+        .all(|axis| {
+            let (min, max) = project(polygon, axis);
+            let c = center.dot(axis);
+            c + radius > min && c - radius < max
+        })
+}
+
+/// Uniform grid over bounding circles used as the broad phase.
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<usize>>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+        }
+    }
+
+    pub fn insert(&mut self, index: usize, center: Vec2, radius: f32) {
+        let min = ((center - radius) / self.cell_size).floor().as_ivec2();
+        let max = ((center + radius) / self.cell_size).floor().as_ivec2();
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                self.cells.entry(IVec2::new(x, y)).or_default().push(index);
+            }
+        }
+    }
+
+    /// Every pair sharing at least one cell, each reported once with the lower index first.
+    pub fn candidate_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for indices in self.cells.values() {
+            for (i, a) in indices.iter().enumerate() {
+                for b in &indices[i + 1..] {
+                    pairs.push((*a.min(b), *a.max(b)));
+                }
+            }
+        }
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+}
+
+pub fn detect_collisions(
+    query: Query<(Entity, &Collider, &Transform, Option<&CollisionLayers>)>,
+    mut collisions: EventWriter<Collision>,
+) {
+    let colliders: Vec<_> = query.iter().collect();
+    let mut grid = SpatialGrid::new(GRID_CELL_SIZE);
+    for (index, (_, collider, transform, _)) in colliders.iter().enumerate() {
+        grid.insert(
+            index,
+            transform.translation.truncate(),
+            collider.bounding_radius(transform),
+        );
+    }
+    for (a, b) in grid.candidate_pairs() {
+        let (a_entity, a_collider, a_transform, a_layers) = colliders[a];
+        let (b_entity, b_collider, b_transform, b_layers) = colliders[b];
+        let a_layers = a_layers.copied().unwrap_or_default();
+        let b_layers = b_layers.copied().unwrap_or_default();
+        if a_layers.interacts(&b_layers)
+            && a_collider.intersects(a_transform, b_collider, b_transform)
+        {
+            collisions.send(Collision(a_entity, b_entity));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_4;
+
+    use super::*;
+
+    fn at(x: f32, y: f32) -> Transform {
+        Transform::from_xyz(x, y, 0.)
+    }
+
+    fn square(half: f32) -> Collider {
+        Collider::ConvexPolygon {
+            points: vec![
+                Vec2::new(-half, -half),
+                Vec2::new(half, -half),
+                Vec2::new(half, half),
+                Vec2::new(-half, half),
+            ],
+        }
+    }
+
+    #[test]
+    fn circles_overlap_by_distance() {
+        let circle = Collider::Circle { radius: 10. };
+        assert!(circle.intersects(&at(0., 0.), &circle, &at(19., 0.)));
+        assert!(!circle.intersects(&at(0., 0.), &circle, &at(21., 0.)));
+    }
+
+    #[test]
+    fn circle_scale_grows_radius() {
+        let circle = Collider::Circle { radius: 10. };
+        let scaled = at(0., 0.).with_scale(Vec3::splat(2.));
+        assert!(circle.intersects(&scaled, &circle, &at(25., 0.)));
+        assert_eq!(circle.bounding_radius(&scaled), 20.);
+    }
+
+    #[test]
+    fn circle_misses_aabb_corner() {
+        let circle = Collider::Circle { radius: 10. };
+        let aabb = Collider::Aabb {
+            half_extents: Vec2::splat(10.),
+        };
+        // Inside the bounding box of both shapes but past the rounded corner.
+        assert!(!circle.intersects(&at(18., 18.), &aabb, &at(0., 0.)));
+        assert!(circle.intersects(&at(18., 0.), &aabb, &at(0., 0.)));
+        assert!(aabb.intersects(&at(0., 0.), &circle, &at(0., 0.)));
+    }
+
+    #[test]
+    fn aabbs_overlap_on_both_axes() {
+        let aabb = Collider::Aabb {
+            half_extents: Vec2::new(10., 5.),
+        };
+        assert!(aabb.intersects(&at(0., 0.), &aabb, &at(15., 8.)));
+        assert!(!aabb.intersects(&at(0., 0.), &aabb, &at(15., 11.)));
+    }
+
+    #[test]
+    fn rotated_polygon_uses_separating_axis() {
+        let polygon = square(10.);
+        let rotated = at(24., 0.).with_rotation(Quat::from_rotation_z(FRAC_PI_4));
+        // Rotated 45° the square reaches ~14.1 towards the other one.
+        assert!(polygon.intersects(&at(0., 0.), &polygon, &rotated));
+        assert!(!polygon.intersects(&at(0., 0.), &polygon, &at(24., 0.)));
+    }
+
+    #[test]
+    fn triangle_and_circle_separated_by_hypotenuse() {
+        let triangle = Collider::ConvexPolygon {
+            points: vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(0., 10.)],
+        };
+        let circle = Collider::Circle { radius: 2. };
+        assert!(!triangle.intersects(&at(0., 0.), &circle, &at(8., 8.)));
+        assert!(triangle.intersects(&at(0., 0.), &circle, &at(5., 5.)));
+    }
+
+    #[test]
+    fn grid_only_pairs_nearby_entries() {
+        let mut grid = SpatialGrid::new(100.);
+        grid.insert(0, Vec2::new(10., 10.), 5.);
+        grid.insert(1, Vec2::new(95., 10.), 10.);
+        grid.insert(2, Vec2::new(500., 500.), 5.);
+        grid.insert(3, Vec2::new(-10., 10.), 15.);
+        assert_eq!(grid.candidate_pairs(), vec![(0, 1), (0, 3), (1, 3)]);
+    }
+
+    #[test]
+    fn layers_must_accept_each_other() {
+        let bullet = CollisionLayers::new(CollisionLayers::BULLET, CollisionLayers::ASTEROID);
+        let asteroid = CollisionLayers::new(
+            CollisionLayers::ASTEROID,
+            CollisionLayers::BULLET | CollisionLayers::PLAYER,
+        );
+        let player = CollisionLayers::new(CollisionLayers::PLAYER, CollisionLayers::ASTEROID);
+        assert!(bullet.interacts(&asteroid));
+        assert!(player.interacts(&asteroid));
+        assert!(!bullet.interacts(&player));
+        assert!(!asteroid.interacts(&asteroid));
+    }
+}
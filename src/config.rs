@@ -0,0 +1,1067 @@
+use std::time::Duration;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    asteroids::AsteroidSize, EnemyKind, GameAssets, PowerUpKind, ReplayPlayback, WeaponPattern,
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MovementMode {
+    /// The ship moves at full speed while a key is held and stops when it is released.
+    Arcade,
+    /// Keys fire thrusters, the ship speeds up and drifts to a halt.
+    Newtonian,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PlayerConfig {
+    pub movement_mode: MovementMode,
+    /// Top speed in either mode.
+    pub movement_speed: f32,
+    /// Thruster acceleration in newtonian mode.
+    pub acceleration: f32,
+    /// Fraction of the speed lost per second when coasting in newtonian mode.
+    pub drag: f32,
+    /// Share of the screen height, from the bottom, the ship may fly in. 0 keeps it on one line.
+    pub vertical_band: f32,
+    /// Degrees the ship tilts at full sideways speed.
+    pub bank_angle: f32,
+    pub size: f32,
+    pub hearts: usize,
+    /// Half hearts lost when ramming an asteroid or enemy, bullets deal their own damage.
+    pub collision_damage: u32,
+    /// Seconds the ship can't be hurt after taking a hit.
+    pub invulnerability: f32,
+    pub blink_interval: f32,
+    /// Hits the regenerating shield absorbs before hearts are lost, 0 disables it.
+    pub shield_points: u32,
+    /// Seconds without damage for one shield point to come back.
+    pub shield_regen_time: f32,
+    /// Sideways speed the ship is pushed away with when hit.
+    pub knockback: f32,
+    /// How quickly the knockback dies down.
+    pub knockback_drag: f32,
+    /// Seconds a teammate has to stay close to a downed ship to bring it back.
+    pub revive_time: f32,
+    /// Distance within which a teammate revives a downed ship.
+    pub revive_radius: f32,
+    /// Half hearts a revived ship comes back with.
+    pub revive_health: u32,
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self {
+            movement_mode: MovementMode::Arcade,
+            movement_speed: 600.0,
+            acceleration: 2400.0,
+            drag: 3.0,
+            vertical_band: 0.35,
+            bank_angle: 20.0,
+            size: 80.0,
+            hearts: 3,
+            collision_damage: 2,
+            invulnerability: 1.5,
+            blink_interval: 0.1,
+            shield_points: 1,
+            shield_regen_time: 6.,
+            knockback: 600.,
+            knockback_drag: 8.,
+            revive_time: 3.,
+            revive_radius: 120.,
+            revive_health: 2,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BulletConfig {
+    pub size: f32,
+}
+
+impl Default for BulletConfig {
+    fn default() -> Self {
+        Self { size: 20. }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WeaponConfig {
+    pub name: String,
+    /// Seconds between two rounds.
+    pub fire_cooldown: f32,
+    pub magazine: usize,
+    /// Spare rounds carried for reloading, unlimited when left out.
+    #[serde(default)]
+    pub reserve: Option<usize>,
+    pub reload_time: f32,
+    pub damage: u32,
+    pub speed: f32,
+    pub pattern: WeaponPattern,
+}
+
+fn default_weapons() -> Vec<WeaponConfig> {
+    vec![
+        WeaponConfig {
+            name: "Blaster".to_string(),
+            fire_cooldown: 0.1,
+            magazine: 60,
+            reserve: None,
+            reload_time: 2.,
+            damage: 1,
+            speed: 400.,
+            pattern: WeaponPattern::Single,
+        },
+        WeaponConfig {
+            name: "Scatter".to_string(),
+            fire_cooldown: 0.4,
+            magazine: 20,
+            reserve: None,
+            reload_time: 2.5,
+            damage: 1,
+            speed: 400.,
+            pattern: WeaponPattern::Spread {
+                count: 5,
+                angle: 40.,
+            },
+        },
+        WeaponConfig {
+            name: "Burst".to_string(),
+            fire_cooldown: 0.35,
+            magazine: 30,
+            reserve: None,
+            reload_time: 2.,
+            damage: 1,
+            speed: 550.,
+            pattern: WeaponPattern::Burst {
+                count: 3,
+                interval: 0.06,
+            },
+        },
+        WeaponConfig {
+            name: "Laser".to_string(),
+            fire_cooldown: 0.6,
+            magazine: 8,
+            reserve: Some(24),
+            reload_time: 3.,
+            damage: 2,
+            speed: 1500.,
+            pattern: WeaponPattern::Laser { length: 300. },
+        },
+        WeaponConfig {
+            name: "Homing".to_string(),
+            fire_cooldown: 0.5,
+            magazine: 12,
+            reserve: Some(36),
+            reload_time: 3.,
+            damage: 2,
+            speed: 300.,
+            pattern: WeaponPattern::Homing { turn_rate: 4. },
+        },
+    ]
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AsteroidSizeConfig {
+    /// Asteroids of this size in a wave at scale 1.
+    pub count: usize,
+    pub size: f32,
+    pub hit_points: u32,
+    pub score: i64,
+    /// Fragments of the next smaller size spawned when destroyed.
+    pub fragments: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AsteroidConfig {
+    pub max_drift: f32,
+    /// Horizontal speed added to the outermost fragments when an asteroid splits.
+    pub fragment_spread: f32,
+    pub large: AsteroidSizeConfig,
+    pub medium: AsteroidSizeConfig,
+    pub small: AsteroidSizeConfig,
+}
+
+impl AsteroidConfig {
+    pub fn get(&self, size: AsteroidSize) -> &AsteroidSizeConfig {
+        match size {
+            AsteroidSize::Large => &self.large,
+            AsteroidSize::Medium => &self.medium,
+            AsteroidSize::Small => &self.small,
+        }
+    }
+
+    /// Asteroids of each size in a wave, the configured counts times `scale`.
+    pub fn wave_counts(&self, scale: f32) -> SizeMix {
+        let count = |size| (self.get(size).count as f32 * scale).round() as u32;
+        SizeMix {
+            large: count(AsteroidSize::Large),
+            medium: count(AsteroidSize::Medium),
+            small: count(AsteroidSize::Small),
+        }
+    }
+}
+
+impl Default for AsteroidConfig {
+    fn default() -> Self {
+        Self {
+            max_drift: 40.0,
+            fragment_spread: 120.0,
+            large: AsteroidSizeConfig {
+                count: 5,
+                size: 80.,
+                hit_points: 3,
+                score: 50,
+                fragments: 2,
+            },
+            medium: AsteroidSizeConfig {
+                count: 10,
+                size: 50.,
+                hit_points: 2,
+                score: 100,
+                fragments: 2,
+            },
+            small: AsteroidSizeConfig {
+                count: 5,
+                size: 30.,
+                hit_points: 1,
+                score: 150,
+                fragments: 0,
+            },
+        }
+    }
+}
+
+/// A number per asteroid size, the weights used to pick the size of each spawned asteroid
+/// or how many of each a wave has left.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SizeMix {
+    pub large: u32,
+    pub medium: u32,
+    pub small: u32,
+}
+
+impl SizeMix {
+    pub fn weight(&self, size: AsteroidSize) -> u32 {
+        match size {
+            AsteroidSize::Large => self.large,
+            AsteroidSize::Medium => self.medium,
+            AsteroidSize::Small => self.small,
+        }
+    }
+
+    pub fn weight_mut(&mut self, size: AsteroidSize) -> &mut u32 {
+        match size {
+            AsteroidSize::Large => &mut self.large,
+            AsteroidSize::Medium => &mut self.medium,
+            AsteroidSize::Small => &mut self.small,
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        AsteroidSize::ALL
+            .iter()
+            .map(|size| self.weight(*size))
+            .sum()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WaveDefinition {
+    /// Scales the per-size asteroid counts, at 1 the wave has `asteroids.<size>.count` of each.
+    pub scale: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// Seconds between two asteroids of the wave.
+    pub spawn_interval: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WaveConfig {
+    pub waves: Vec<WaveDefinition>,
+    /// Seconds between clearing a wave and the next one starting.
+    pub break_time: f32,
+    /// Applied once per wave past the last defined one.
+    pub scale_growth: f32,
+    pub speed_growth: f32,
+    pub interval_decay: f32,
+    /// Every this many waves a boss shows up instead of asteroids, 0 disables bosses.
+    pub boss_every: u32,
+}
+
+impl WaveConfig {
+    pub fn is_boss_wave(&self, number: u32) -> bool {
+        self.boss_every > 0 && number.is_multiple_of(self.boss_every)
+    }
+
+    /// Definition of the 1-based wave `number`, ramping up the last defined wave once they run out.
+    pub fn wave(&self, number: u32) -> WaveDefinition {
+        let index = (number.max(1) - 1) as usize;
+        let Some(last) = self.waves.last() else {
+            return WaveDefinition::default();
+        };
+        if let Some(wave) = self.waves.get(index) {
+            return wave.clone();
+        }
+        let extra = (index + 1 - self.waves.len()) as i32;
+        let speed = self.speed_growth.powi(extra);
+        WaveDefinition {
+            scale: last.scale + self.scale_growth * extra as f32,
+            min_speed: last.min_speed * speed,
+            max_speed: last.max_speed * speed,
+            spawn_interval: last.spawn_interval * self.interval_decay.powi(extra),
+        }
+    }
+}
+
+impl Default for WaveDefinition {
+    fn default() -> Self {
+        Self {
+            scale: 0.5,
+            min_speed: 150.0,
+            max_speed: 250.0,
+            spawn_interval: 0.8,
+        }
+    }
+}
+
+impl Default for WaveConfig {
+    fn default() -> Self {
+        Self {
+            waves: vec![
+                WaveDefinition::default(),
+                WaveDefinition {
+                    scale: 0.75,
+                    min_speed: 150.0,
+                    max_speed: 300.0,
+                    spawn_interval: 0.6,
+                },
+                WaveDefinition {
+                    scale: 1.0,
+                    min_speed: 150.0,
+                    max_speed: 400.0,
+                    spawn_interval: 0.5,
+                },
+            ],
+            break_time: 3.0,
+            scale_growth: 0.15,
+            speed_growth: 1.05,
+            interval_decay: 0.95,
+            boss_every: 3,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EnemyTypeConfig {
+    pub health: u32,
+    pub score: i64,
+    pub size: f32,
+    pub speed: f32,
+    /// Seconds between two shots.
+    pub fire_interval: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EnemyConfig {
+    pub first_spawn_delay: f32,
+    pub spawn_interval: f32,
+    pub bullet_speed: f32,
+    /// Weavers come in rows of this many ships.
+    pub formation_size: usize,
+    pub weave_amplitude: f32,
+    pub weave_frequency: f32,
+    pub strafer: EnemyTypeConfig,
+    pub diver: EnemyTypeConfig,
+    pub weaver: EnemyTypeConfig,
+}
+
+impl EnemyConfig {
+    pub fn get(&self, kind: EnemyKind) -> &EnemyTypeConfig {
+        match kind {
+            EnemyKind::Strafer => &self.strafer,
+            EnemyKind::Diver => &self.diver,
+            EnemyKind::Weaver => &self.weaver,
+        }
+    }
+}
+
+impl Default for EnemyConfig {
+    fn default() -> Self {
+        Self {
+            first_spawn_delay: 15.0,
+            spawn_interval: 12.0,
+            bullet_speed: 300.0,
+            formation_size: 4,
+            weave_amplitude: 80.0,
+            weave_frequency: 2.0,
+            strafer: EnemyTypeConfig {
+                health: 3,
+                score: 200,
+                size: 70.,
+                speed: 150.,
+                fire_interval: 1.5,
+            },
+            diver: EnemyTypeConfig {
+                health: 2,
+                score: 250,
+                size: 60.,
+                speed: 200.,
+                fire_interval: 1.0,
+            },
+            weaver: EnemyTypeConfig {
+                health: 1,
+                score: 100,
+                size: 50.,
+                speed: 120.,
+                fire_interval: 2.5,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PowerUpDrop {
+    pub kind: PowerUpKind,
+    pub weight: u32,
+    /// Seconds the effect lasts, ignored for instant pickups.
+    #[serde(default)]
+    pub duration: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PowerUpConfig {
+    /// Chance for a destroyed asteroid to drop anything, the drop table picks what.
+    pub drop_chance: f32,
+    pub drops: Vec<PowerUpDrop>,
+    pub fall_speed: f32,
+    pub size: f32,
+    pub max_hearts: usize,
+    /// Fire cooldown runs this many times faster with rapid fire.
+    pub rapid_fire_factor: f32,
+    /// Degrees between the bullets of a spread shot.
+    pub spread_angle: f32,
+    pub score_multiplier: i64,
+}
+
+impl PowerUpConfig {
+    pub fn duration(&self, kind: PowerUpKind) -> f32 {
+        self.drops
+            .iter()
+            .find(|drop| drop.kind == kind)
+            .map_or(0., |drop| drop.duration)
+    }
+}
+
+impl Default for PowerUpConfig {
+    fn default() -> Self {
+        let drop = |kind, weight, duration| PowerUpDrop {
+            kind,
+            weight,
+            duration,
+        };
+        Self {
+            drop_chance: 0.15,
+            drops: vec![
+                drop(PowerUpKind::ExtraHeart, 1, 0.),
+                drop(PowerUpKind::InstantReload, 3, 0.),
+                drop(PowerUpKind::Shield, 2, 6.),
+                drop(PowerUpKind::RapidFire, 2, 8.),
+                drop(PowerUpKind::SpreadShot, 2, 8.),
+                drop(PowerUpKind::ScoreMultiplier, 2, 10.),
+            ],
+            fall_speed: 120.,
+            size: 30.,
+            max_hearts: 5,
+            rapid_fire_factor: 2.,
+            spread_angle: 15.,
+            score_multiplier: 2,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DifficultyMode {
+    /// Discrete waves from the `waves` section.
+    Waves,
+    /// Endless stream scaled by the difficulty curve.
+    Adaptive,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DifficultyConfig {
+    pub mode: DifficultyMode,
+    /// Play time and score each worth one difficulty level.
+    pub seconds_per_level: f32,
+    pub score_per_level: f32,
+    pub max_level: f32,
+    /// Values at level 0.
+    pub min_speed: f32,
+    pub max_speed: f32,
+    pub spawn_interval: f32,
+    pub size_mix: SizeMix,
+    /// Fraction added per level to the speed range, the spawn rate and the drift.
+    pub speed_per_level: f32,
+    pub density_per_level: f32,
+    pub drift_per_level: f32,
+}
+
+impl Default for DifficultyConfig {
+    fn default() -> Self {
+        Self {
+            mode: DifficultyMode::Waves,
+            seconds_per_level: 30.0,
+            score_per_level: 2000.0,
+            max_level: 10.0,
+            min_speed: 150.0,
+            max_speed: 250.0,
+            spawn_interval: 0.8,
+            size_mix: SizeMix {
+                large: 1,
+                medium: 2,
+                small: 1,
+            },
+            speed_per_level: 0.1,
+            density_per_level: 0.15,
+            drift_per_level: 0.2,
+        }
+    }
+}
+
+/// More stars than this only slow the game down.
+const MAX_STARS: usize = 10_000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StarConfig {
+    pub count: usize,
+    pub falling_speed: f32,
+}
+
+impl Default for StarConfig {
+    fn default() -> Self {
+        Self {
+            count: 500,
+            falling_speed: 150.0,
+        }
+    }
+}
+
+/// Tuning values read from `assets/config.ron`, copied into a resource once loaded.
+#[derive(Asset, TypePath, Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GameConfig {
+    pub player: PlayerConfig,
+    pub bullet: BulletConfig,
+    /// Weapons the player cycles through, the first one is equipped at the start.
+    pub weapons: Vec<WeaponConfig>,
+    pub asteroids: AsteroidConfig,
+    pub waves: WaveConfig,
+    pub difficulty: DifficultyConfig,
+    pub enemies: EnemyConfig,
+    pub powerups: PowerUpConfig,
+    pub stars: StarConfig,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            player: default(),
+            bullet: default(),
+            weapons: default_weapons(),
+            asteroids: default(),
+            waves: default(),
+            difficulty: default(),
+            enemies: default(),
+            powerups: default(),
+            stars: default(),
+        }
+    }
+}
+
+fn positive(errors: &mut Vec<String>, name: &str, value: f32) {
+    if !(value.is_finite() && value > 0.) {
+        errors.push(format!("{name} must be positive, got {value}"));
+    }
+}
+
+fn not_negative(errors: &mut Vec<String>, name: &str, value: f32) {
+    if !(value.is_finite() && value >= 0.) {
+        errors.push(format!("{name} must not be negative, got {value}"));
+    }
+}
+
+impl GameConfig {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        positive(
+            &mut errors,
+            "player.movement_speed",
+            self.player.movement_speed,
+        );
+        positive(&mut errors, "player.size", self.player.size);
+        positive(&mut errors, "player.acceleration", self.player.acceleration);
+        positive(&mut errors, "player.drag", self.player.drag);
+        if !(0. ..=1.).contains(&self.player.vertical_band) {
+            errors.push(format!(
+                "player.vertical_band must be between 0 and 1, got {}",
+                self.player.vertical_band
+            ));
+        }
+        positive(
+            &mut errors,
+            "player.invulnerability",
+            self.player.invulnerability,
+        );
+        positive(
+            &mut errors,
+            "player.blink_interval",
+            self.player.blink_interval,
+        );
+        positive(
+            &mut errors,
+            "player.shield_regen_time",
+            self.player.shield_regen_time,
+        );
+        positive(
+            &mut errors,
+            "player.knockback_drag",
+            self.player.knockback_drag,
+        );
+        positive(&mut errors, "player.revive_time", self.player.revive_time);
+        positive(
+            &mut errors,
+            "player.revive_radius",
+            self.player.revive_radius,
+        );
+        if self.player.revive_health == 0 {
+            errors.push("player.revive_health must be at least 1".to_string());
+        }
+        not_negative(&mut errors, "player.knockback", self.player.knockback);
+        positive(&mut errors, "bullet.size", self.bullet.size);
+        positive(&mut errors, "asteroids.max_drift", self.asteroids.max_drift);
+        positive(&mut errors, "stars.falling_speed", self.stars.falling_speed);
+        if self.player.hearts == 0 {
+            errors.push("player.hearts must be at least 1".to_string());
+        }
+        if self.weapons.is_empty() {
+            errors.push("weapons must define at least one weapon".to_string());
+        }
+        for weapon in &self.weapons {
+            let name = format!("weapons.{}", weapon.name);
+            positive(
+                &mut errors,
+                &format!("{name}.fire_cooldown"),
+                weapon.fire_cooldown,
+            );
+            positive(
+                &mut errors,
+                &format!("{name}.reload_time"),
+                weapon.reload_time,
+            );
+            positive(&mut errors, &format!("{name}.speed"), weapon.speed);
+            if weapon.magazine == 0 {
+                errors.push(format!("{name}.magazine must be at least 1"));
+            }
+            match weapon.pattern {
+                WeaponPattern::Spread { count, .. } | WeaponPattern::Burst { count, .. }
+                    if count == 0 =>
+                {
+                    errors.push(format!("{name}.pattern count must be at least 1"));
+                }
+                WeaponPattern::Laser { length } => {
+                    positive(&mut errors, &format!("{name}.pattern.length"), length);
+                }
+                _ => {}
+            }
+        }
+        for size in AsteroidSize::ALL {
+            let name = format!("asteroids.{}", size.name());
+            let asteroid = self.asteroids.get(size);
+            positive(&mut errors, &format!("{name}.size"), asteroid.size);
+            if asteroid.hit_points == 0 {
+                errors.push(format!("{name}.hit_points must be at least 1"));
+            }
+        }
+        if AsteroidSize::ALL
+            .iter()
+            .all(|size| self.asteroids.get(*size).count == 0)
+        {
+            errors.push("asteroids need a count of at least 1 for some size".to_string());
+        }
+        if self.waves.waves.is_empty() {
+            errors.push("waves.waves must define at least one wave".to_string());
+        }
+        for (i, wave) in self.waves.waves.iter().enumerate() {
+            let name = format!("waves.waves[{i}]");
+            positive(&mut errors, &format!("{name}.scale"), wave.scale);
+            positive(&mut errors, &format!("{name}.min_speed"), wave.min_speed);
+            positive(
+                &mut errors,
+                &format!("{name}.spawn_interval"),
+                wave.spawn_interval,
+            );
+            if wave.max_speed <= wave.min_speed {
+                errors.push(format!(
+                    "{name}.max_speed ({}) must be greater than {name}.min_speed ({})",
+                    wave.max_speed, wave.min_speed
+                ));
+            }
+        }
+        positive(&mut errors, "waves.break_time", self.waves.break_time);
+        not_negative(&mut errors, "waves.scale_growth", self.waves.scale_growth);
+        positive(&mut errors, "waves.speed_growth", self.waves.speed_growth);
+        positive(
+            &mut errors,
+            "waves.interval_decay",
+            self.waves.interval_decay,
+        );
+        let difficulty = &self.difficulty;
+        positive(
+            &mut errors,
+            "difficulty.seconds_per_level",
+            difficulty.seconds_per_level,
+        );
+        positive(
+            &mut errors,
+            "difficulty.score_per_level",
+            difficulty.score_per_level,
+        );
+        positive(&mut errors, "difficulty.min_speed", difficulty.min_speed);
+        positive(
+            &mut errors,
+            "difficulty.spawn_interval",
+            difficulty.spawn_interval,
+        );
+        if difficulty.max_speed <= difficulty.min_speed {
+            errors.push(format!(
+                "difficulty.max_speed ({}) must be greater than difficulty.min_speed ({})",
+                difficulty.max_speed, difficulty.min_speed
+            ));
+        }
+        not_negative(&mut errors, "difficulty.max_level", difficulty.max_level);
+        not_negative(
+            &mut errors,
+            "difficulty.speed_per_level",
+            difficulty.speed_per_level,
+        );
+        not_negative(
+            &mut errors,
+            "difficulty.density_per_level",
+            difficulty.density_per_level,
+        );
+        not_negative(
+            &mut errors,
+            "difficulty.drift_per_level",
+            difficulty.drift_per_level,
+        );
+        if AsteroidSize::ALL
+            .iter()
+            .all(|size| difficulty.size_mix.weight(*size) == 0)
+        {
+            errors.push("difficulty.size_mix needs at least one non-zero weight".to_string());
+        }
+        let enemies = &self.enemies;
+        positive(
+            &mut errors,
+            "enemies.spawn_interval",
+            enemies.spawn_interval,
+        );
+        positive(&mut errors, "enemies.bullet_speed", enemies.bullet_speed);
+        if enemies.formation_size == 0 {
+            errors.push("enemies.formation_size must be at least 1".to_string());
+        }
+        not_negative(
+            &mut errors,
+            "enemies.weave_amplitude",
+            enemies.weave_amplitude,
+        );
+        not_negative(
+            &mut errors,
+            "enemies.weave_frequency",
+            enemies.weave_frequency,
+        );
+        for kind in EnemyKind::ALL {
+            let name = format!("enemies.{}", kind.name());
+            let enemy = enemies.get(kind);
+            positive(&mut errors, &format!("{name}.size"), enemy.size);
+            positive(&mut errors, &format!("{name}.speed"), enemy.speed);
+            positive(
+                &mut errors,
+                &format!("{name}.fire_interval"),
+                enemy.fire_interval,
+            );
+            if enemy.health == 0 {
+                errors.push(format!("{name}.health must be at least 1"));
+            }
+        }
+        let powerups = &self.powerups;
+        if !(0. ..=1.).contains(&powerups.drop_chance) {
+            errors.push(format!(
+                "powerups.drop_chance must be between 0 and 1, got {}",
+                powerups.drop_chance
+            ));
+        }
+        for (i, drop) in powerups.drops.iter().enumerate() {
+            not_negative(
+                &mut errors,
+                &format!("powerups.drops[{i}].duration"),
+                drop.duration,
+            );
+        }
+        positive(&mut errors, "powerups.fall_speed", powerups.fall_speed);
+        positive(&mut errors, "powerups.size", powerups.size);
+        positive(
+            &mut errors,
+            "powerups.rapid_fire_factor",
+            powerups.rapid_fire_factor,
+        );
+        not_negative(&mut errors, "powerups.spread_angle", powerups.spread_angle);
+        if powerups.max_hearts == 0 {
+            errors.push("powerups.max_hearts must be at least 1".to_string());
+        }
+        if powerups.score_multiplier < 1 {
+            errors.push(format!(
+                "powerups.score_multiplier must be at least 1, got {}",
+                powerups.score_multiplier
+            ));
+        }
+        if self.stars.count > MAX_STARS {
+            errors.push(format!(
+                "stars.count must be at most {MAX_STARS}, got {}",
+                self.stars.count
+            ));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Durations in the config are written in seconds. Going straight from `f32` would leave
+/// `0.1` a hair over 100ms, so they are rounded to whole microseconds.
+pub fn duration_from_secs(secs: f32) -> Duration {
+    Duration::from_micros((f64::from(secs) * 1_000_000.).round() as u64)
+}
+
+#[derive(Default)]
+pub struct GameConfigLoader;
+
+impl AssetLoader for GameConfigLoader {
+    type Asset = GameConfig;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<GameConfig, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+/// Falls back to the built-in defaults when the loaded config doesn't validate.
+pub fn validated_config(config: &GameConfig) -> GameConfig {
+    match config.validate() {
+        Ok(()) => config.clone(),
+        Err(errors) => {
+            for error in errors {
+                error!("invalid config.ron: {error}");
+            }
+            GameConfig::default()
+        }
+    }
+}
+
+/// Applies edits to `config.ron` as they are saved. While a replay is watched it keeps
+/// the config it was recorded with, the edit is applied once it's over.
+pub fn reload_config(
+    mut events: EventReader<AssetEvent<GameConfig>>,
+    game_assets: Res<GameAssets>,
+    configs: Res<Assets<GameConfig>>,
+    mut config: ResMut<GameConfig>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut pending: Local<bool>,
+) {
+    for event in events.read() {
+        if event.is_modified(&game_assets.config) {
+            *pending = true;
+        }
+    }
+    if !*pending || playback.is_some() {
+        return;
+    }
+    let Some(reloaded) = configs.get(&game_assets.config) else {
+        return;
+    };
+    *pending = false;
+    match reloaded.validate() {
+        Ok(()) => {
+            info!("reloaded config.ron");
+            *config = reloaded.clone();
+        }
+        Err(errors) => {
+            for error in errors {
+                error!("keeping previous config, config.ron is invalid: {error}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(change: impl FnOnce(&mut GameConfig)) -> Result<(), Vec<String>> {
+        let mut config = GameConfig::default();
+        change(&mut config);
+        config.validate()
+    }
+
+    /// Checks that the change is rejected with an error about `field`.
+    fn assert_rejected(field: &str, change: impl FnOnce(&mut GameConfig)) {
+        let errors = validate(change).unwrap_err();
+        assert!(
+            errors.iter().all(|error| error.starts_with(field)),
+            "expected only {field} errors, got {errors:?}"
+        );
+    }
+
+    #[test]
+    fn shipped_config_is_valid() {
+        let config: GameConfig = ron::from_str(include_str!("../assets/config.ron")).unwrap();
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn player_section() {
+        assert_eq!(validate(|config| config.player.vertical_band = 0.), Ok(()));
+        assert_rejected("player.revive_time", |config| {
+            config.player.revive_time = -1.
+        });
+    }
+
+    #[test]
+    fn bullet_section() {
+        assert_eq!(validate(|config| config.bullet.size = 5.), Ok(()));
+        assert_rejected("bullet.size", |config| config.bullet.size = 0.);
+    }
+
+    #[test]
+    fn weapons_section() {
+        assert_eq!(validate(|config| config.weapons.truncate(1)), Ok(()));
+        assert_rejected("weapons.Blaster.magazine", |config| {
+            config.weapons[0].magazine = 0
+        });
+        assert_rejected("weapons must define", |config| config.weapons.clear());
+    }
+
+    #[test]
+    fn asteroids_section() {
+        assert_eq!(
+            validate(|config| config.asteroids.large.fragments = 0),
+            Ok(())
+        );
+        assert_rejected("asteroids.small.hit_points", |config| {
+            config.asteroids.small.hit_points = 0
+        });
+        assert_eq!(validate(|config| config.asteroids.large.count = 0), Ok(()));
+        assert_rejected("asteroids need a count", |config| {
+            config.asteroids.large.count = 0;
+            config.asteroids.medium.count = 0;
+            config.asteroids.small.count = 0;
+        });
+    }
+
+    #[test]
+    fn waves_section() {
+        assert_eq!(validate(|config| config.waves.boss_every = 0), Ok(()));
+        assert_rejected("waves.waves[1].scale", |config| {
+            config.waves.waves[1].scale = 0.
+        });
+        assert_rejected("waves.waves[0].max_speed", |config| {
+            config.waves.waves[0].max_speed = config.waves.waves[0].min_speed
+        });
+    }
+
+    #[test]
+    fn waves_scale_the_per_size_counts() {
+        let config = GameConfig::default();
+        let counts = |wave| config.asteroids.wave_counts(config.waves.wave(wave).scale);
+        assert_eq!(
+            counts(1),
+            SizeMix {
+                large: 3,
+                medium: 5,
+                small: 3,
+            }
+        );
+        // Two waves past the last defined one, at scale 1.3.
+        assert_eq!(
+            counts(5),
+            SizeMix {
+                large: 7,
+                medium: 13,
+                small: 7,
+            }
+        );
+    }
+
+    #[test]
+    fn difficulty_section() {
+        assert_eq!(
+            validate(|config| config.difficulty.density_per_level = 0.),
+            Ok(())
+        );
+        assert_rejected("difficulty.density_per_level", |config| {
+            config.difficulty.density_per_level = -0.5
+        });
+        assert_rejected("difficulty.max_speed", |config| {
+            config.difficulty.max_speed = 0.
+        });
+    }
+
+    #[test]
+    fn enemies_section() {
+        assert_eq!(validate(|config| config.enemies.formation_size = 1), Ok(()));
+        assert_rejected("enemies.formation_size", |config| {
+            config.enemies.formation_size = 0
+        });
+        assert_rejected("enemies.diver.fire_interval", |config| {
+            config.enemies.diver.fire_interval = 0.
+        });
+        assert_rejected("enemies.weave_frequency", |config| {
+            config.enemies.weave_frequency = f32::NAN
+        });
+    }
+
+    #[test]
+    fn powerups_section() {
+        assert_eq!(validate(|config| config.powerups.drop_chance = 1.), Ok(()));
+        assert_rejected("powerups.drops[2].duration", |config| {
+            config.powerups.drops[2].duration = -6.
+        });
+        assert_rejected("powerups.score_multiplier", |config| {
+            config.powerups.score_multiplier = 0
+        });
+    }
+
+    #[test]
+    fn stars_section() {
+        assert_eq!(validate(|config| config.stars.count = 0), Ok(()));
+        assert_rejected("stars.count", |config| config.stars.count = 1_000_000);
+    }
+}
@@ -0,0 +1,425 @@
+use bevy::{prelude::*, utils::HashSet, window::PrimaryWindow};
+
+use crate::{
+    play_area, player_layers, Bullet, BulletHitPlayer, CollisionLayers, GameConfig, Player,
+    PlayerConfig, PlayerHitAsteroid, PlayerHitEnemy, PlayerIndex, PowerUpKind, PowerUps,
+};
+
+const SHIELD_TINT: Color = Color::srgb(0.6, 0.85, 1.);
+/// Knockback below this speed is dropped.
+const KNOCKBACK_STOP: f32 = 5.;
+
+/// Hit points counted in half hearts.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Health {
+    pub fn from_hearts(hearts: usize) -> Self {
+        let max = hearts as u32 * 2;
+        Self { current: max, max }
+    }
+
+    pub fn damage(&mut self, amount: u32) {
+        self.current = self.current.saturating_sub(amount);
+    }
+
+    pub fn heal(&mut self, amount: u32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current == 0
+    }
+}
+
+/// Grace period after a hit, during which the ship blinks and can't be hurt.
+#[derive(Component)]
+pub struct Invulnerable {
+    pub timer: Timer,
+    blink: Timer,
+}
+
+impl Invulnerable {
+    pub fn new(config: &PlayerConfig) -> Self {
+        Self {
+            timer: Timer::from_seconds(config.invulnerability, TimerMode::Once),
+            blink: Timer::from_seconds(config.blink_interval, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Shield points soak up hits before hearts do and come back one by one after a quiet spell.
+#[derive(Component)]
+pub struct ShieldLayer {
+    pub points: u32,
+    pub regen: Timer,
+}
+
+impl ShieldLayer {
+    pub fn new(config: &PlayerConfig) -> Self {
+        Self {
+            points: config.shield_points,
+            regen: Timer::from_seconds(config.shield_regen_time, TimerMode::Repeating),
+        }
+    }
+}
+
+/// A ship out of health in co-op. It drifts along as a husk that nothing collides with
+/// until a teammate hovers close enough for long enough.
+#[derive(Component)]
+pub struct Downed {
+    pub revive: Timer,
+}
+
+impl Downed {
+    pub fn new(config: &PlayerConfig) -> Self {
+        Self {
+            revive: Timer::from_seconds(config.revive_time, TimerMode::Once),
+        }
+    }
+}
+
+/// Filter for players still in the fight.
+pub type ActivePlayer = (With<Player>, Without<Downed>);
+
+/// Sideways push away from whatever hit the ship, in pixels per second.
+#[derive(Component)]
+pub struct Knockback {
+    pub velocity: f32,
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn damage_player(
+    mut commands: Commands,
+    mut asteroid_hits: EventReader<PlayerHitAsteroid>,
+    mut enemy_hits: EventReader<PlayerHitEnemy>,
+    mut bullet_hits: EventReader<BulletHitPlayer>,
+    mut player_query: Query<
+        (&Transform, &mut Health, &PowerUps, Option<&mut ShieldLayer>),
+        (ActivePlayer, Without<Invulnerable>),
+    >,
+    sources_query: Query<&Transform, Without<Player>>,
+    bullets_query: Query<&Bullet>,
+    config: Res<GameConfig>,
+) {
+    // Invulnerability only lands once commands are applied, so later hits this frame are
+    // dropped here.
+    let mut hit_players = HashSet::new();
+    let hits = asteroid_hits
+        .read()
+        .map(|hit| (hit.player, hit.asteroid))
+        .chain(enemy_hits.read().map(|hit| (hit.player, hit.enemy)))
+        .chain(bullet_hits.read().map(|hit| (hit.player, hit.bullet)))
+        .collect::<Vec<_>>();
+    for (player, source) in hits {
+        let damage = bullets_query
+            .get(source)
+            .map_or(config.player.collision_damage, |bullet| bullet.damage);
+        if hit_players.contains(&player) {
+            continue;
+        }
+        let Ok((transform, mut health, powerups, shield)) = player_query.get_mut(player) else {
+            continue;
+        };
+        if powerups.is_active(PowerUpKind::Shield) {
+            debug!("shield absorbed a hit from {source}");
+            continue;
+        }
+        hit_players.insert(player);
+        match shield {
+            Some(mut shield) if shield.points > 0 => {
+                debug!("shield layer absorbed a hit from {source}");
+                shield.points -= 1;
+                shield.regen.reset();
+            }
+            shield => {
+                debug!("player {player} hit by {source}");
+                if let Some(mut shield) = shield {
+                    shield.regen.reset();
+                }
+                health.damage(damage);
+            }
+        }
+        let away = sources_query
+            .get(source)
+            .map(|source| transform.translation.x - source.translation.x)
+            .unwrap_or_default();
+        let direction = if away < 0. { -1. } else { 1. };
+        let mut entity = commands.entity(player);
+        entity.insert(Knockback {
+            velocity: direction * config.player.knockback,
+        });
+        if health.is_dead() {
+            debug!("player {player} is down");
+            entity.insert((
+                Downed::new(&config.player),
+                CollisionLayers::new(CollisionLayers::PLAYER, 0),
+            ));
+        } else {
+            entity.insert(Invulnerable::new(&config.player));
+        }
+    }
+}
+
+/// Blinks the ship while it is invulnerable and shows it normally again afterwards.
+pub fn update_invulnerability(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Invulnerable, &mut Visibility)>,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable, mut visibility) in query.iter_mut() {
+        if invulnerable.timer.tick(time.delta()).finished() {
+            *visibility = Visibility::Inherited;
+            commands.entity(entity).remove::<Invulnerable>();
+            continue;
+        }
+        if invulnerable.blink.tick(time.delta()).just_finished() {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Inherited,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+}
+
+pub fn regenerate_shield(
+    mut query: Query<&mut ShieldLayer, ActivePlayer>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+) {
+    for mut shield in query.iter_mut() {
+        if shield.points < config.player.shield_points {
+            let regenerated = shield.regen.tick(time.delta()).times_finished_this_tick();
+            shield.points = (shield.points + regenerated).min(config.player.shield_points);
+        }
+    }
+}
+
+/// Brings a downed ship back while a flying teammate stays within reach, the timer starts
+/// over whenever they leave.
+pub fn revive_players(
+    mut commands: Commands,
+    mut downed_query: Query<(Entity, &Transform, &mut Downed, &mut Health)>,
+    helpers_query: Query<&Transform, ActivePlayer>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+) {
+    for (entity, transform, mut downed, mut health) in downed_query.iter_mut() {
+        let position = transform.translation.truncate();
+        let helped = helpers_query.iter().any(|helper| {
+            helper.translation.truncate().distance(position) <= config.player.revive_radius
+        });
+        if !helped {
+            downed.revive.reset();
+            continue;
+        }
+        if downed.revive.tick(time.delta()).just_finished() {
+            debug!("player {entity} revived");
+            health.heal(config.player.revive_health);
+            commands
+                .entity(entity)
+                .remove::<Downed>()
+                .insert((player_layers(), Invulnerable::new(&config.player)));
+        }
+    }
+}
+
+/// Colours each ship after its player, light blue while the shield layer is up. Downed
+/// ships are faint and fill back in as the revive progresses.
+pub fn tint_players(mut query: Query<(&PlayerIndex, &ShieldLayer, Option<&Downed>, &mut Sprite)>) {
+    for (index, shield, downed, mut sprite) in query.iter_mut() {
+        sprite.color = match downed {
+            Some(downed) => index
+                .color()
+                .with_alpha(0.25 + 0.75 * downed.revive.fraction()),
+            None if shield.points > 0 => SHIELD_TINT,
+            None => index.color(),
+        };
+    }
+}
+
+pub fn apply_knockback(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform, &mut Knockback)>,
+    win_query: Query<&Window, With<PrimaryWindow>>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+) {
+    let Ok(win) = win_query.get_single() else {
+        return;
+    };
+    let area = play_area(win, &config.player);
+    for (entity, mut transform, mut knockback) in query.iter_mut() {
+        transform.translation.x = (transform.translation.x
+            + knockback.velocity * time.delta_seconds())
+        .clamp(area.min.x, area.max.x);
+        knockback.velocity *= (-config.player.knockback_drag * time.delta_seconds()).exp();
+        if knockback.velocity.abs() < KNOCKBACK_STOP {
+            commands.entity(entity).remove::<Knockback>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::PowerUps;
+
+    fn setup_app() -> (App, Entity) {
+        let mut app = App::new();
+        app.init_resource::<Time>();
+        app.init_resource::<GameConfig>();
+        app.add_event::<PlayerHitAsteroid>()
+            .add_event::<PlayerHitEnemy>()
+            .add_event::<BulletHitPlayer>();
+        app.add_systems(
+            Update,
+            (damage_player, update_invulnerability, regenerate_shield),
+        );
+        let config = GameConfig::default().player;
+        let player = app
+            .world_mut()
+            .spawn((
+                Player::new(&GameConfig::default().weapons),
+                Transform::default(),
+                Visibility::default(),
+                Health::from_hearts(config.hearts),
+                ShieldLayer::new(&config),
+                PowerUps::default(),
+            ))
+            .id();
+        (app, player)
+    }
+
+    fn advance(app: &mut App, millis: u64) {
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_millis(millis));
+        app.update();
+    }
+
+    /// Rams the player with a fresh rock and lets the frame play out.
+    fn hit(app: &mut App, player: Entity) {
+        let asteroid = app.world_mut().spawn(Transform::default()).id();
+        app.world_mut()
+            .send_event(PlayerHitAsteroid { player, asteroid });
+        advance(app, 0);
+    }
+
+    fn health(app: &App, player: Entity) -> u32 {
+        app.world().get::<Health>(player).unwrap().current
+    }
+
+    fn shield(app: &App, player: Entity) -> u32 {
+        app.world().get::<ShieldLayer>(player).unwrap().points
+    }
+
+    #[test]
+    fn health_counts_half_hearts() {
+        let mut health = Health::from_hearts(3);
+        assert_eq!((health.current, health.max), (6, 6));
+        health.damage(1);
+        assert_eq!(health.current, 5);
+        health.heal(3);
+        assert_eq!(health.current, 6);
+        health.damage(4);
+        health.heal(1);
+        assert_eq!(health.current, 3);
+        health.damage(10);
+        assert_eq!(health.current, 0);
+        assert!(health.is_dead());
+    }
+
+    #[test]
+    fn invulnerability_blocks_a_second_hit() {
+        let (mut app, player) = setup_app();
+        app.world_mut()
+            .get_mut::<ShieldLayer>(player)
+            .unwrap()
+            .points = 0;
+        hit(&mut app, player);
+        assert_eq!(health(&app, player), 4);
+        assert!(app.world().get::<Invulnerable>(player).is_some());
+
+        hit(&mut app, player);
+        assert_eq!(health(&app, player), 4);
+
+        advance(&mut app, 1500);
+        assert!(app.world().get::<Invulnerable>(player).is_none());
+        hit(&mut app, player);
+        assert_eq!(health(&app, player), 2);
+    }
+
+    #[test]
+    fn shield_points_absorb_hits_before_hearts() {
+        let (mut app, player) = setup_app();
+        hit(&mut app, player);
+        assert_eq!(shield(&app, player), 0);
+        assert_eq!(health(&app, player), 6);
+
+        advance(&mut app, 1500);
+        hit(&mut app, player);
+        assert_eq!(health(&app, player), 4);
+    }
+
+    #[test]
+    fn shield_comes_back_after_regen_time() {
+        let (mut app, player) = setup_app();
+        hit(&mut app, player);
+        assert_eq!(shield(&app, player), 0);
+
+        advance(&mut app, 5900);
+        assert_eq!(shield(&app, player), 0);
+        advance(&mut app, 100);
+        assert_eq!(shield(&app, player), 1);
+        advance(&mut app, 6000);
+        assert_eq!(shield(&app, player), 1);
+    }
+
+    #[test]
+    fn revive_starts_over_when_the_helper_leaves() {
+        let mut app = App::new();
+        app.init_resource::<Time>();
+        app.init_resource::<GameConfig>();
+        app.add_systems(Update, revive_players);
+        let config = GameConfig::default().player;
+        let mut husk = Health::from_hearts(config.hearts);
+        husk.damage(husk.max);
+        let downed = app
+            .world_mut()
+            .spawn((Transform::default(), Downed::new(&config), husk))
+            .id();
+        let helper = app
+            .world_mut()
+            .spawn((
+                Player::new(&GameConfig::default().weapons),
+                Transform::from_xyz(50., 0., 0.),
+            ))
+            .id();
+        let move_helper = |app: &mut App, x: f32| {
+            app.world_mut()
+                .get_mut::<Transform>(helper)
+                .unwrap()
+                .translation
+                .x = x;
+        };
+
+        advance(&mut app, 2000);
+        move_helper(&mut app, 1000.);
+        advance(&mut app, 100);
+        let revive = &app.world().get::<Downed>(downed).unwrap().revive;
+        assert_eq!(revive.elapsed(), Duration::ZERO);
+
+        move_helper(&mut app, 50.);
+        advance(&mut app, 2000);
+        assert!(app.world().get::<Downed>(downed).is_some());
+        advance(&mut app, 1000);
+        assert!(app.world().get::<Downed>(downed).is_none());
+        assert_eq!(health(&app, downed), config.revive_health);
+    }
+}
@@ -0,0 +1,158 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use rand::Rng;
+
+use crate::{
+    asteroids::{pick_size, spawn_falling_asteroid, Velocity},
+    DifficultyConfig, DifficultyMode, GameAssets, GameConfig, GameRng, Score,
+};
+
+/// Current point on the adaptive difficulty curve, recomputed every frame of a run.
+#[derive(Resource, Clone, Debug)]
+pub struct DifficultyCurve {
+    /// Seconds of unpaused play in this run.
+    pub elapsed: f32,
+    pub level: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    pub max_drift: f32,
+    /// Seconds between two spawned asteroids.
+    pub spawn_interval: f32,
+    /// Seconds since the last spawned asteroid.
+    pub since_spawn: f32,
+}
+
+impl DifficultyCurve {
+    pub fn evaluate(config: &DifficultyConfig, max_drift: f32, elapsed: f32, score: i64) -> Self {
+        let level = (elapsed / config.seconds_per_level
+            + score.max(0) as f32 / config.score_per_level)
+            .min(config.max_level);
+        let speed = 1. + config.speed_per_level * level;
+        Self {
+            elapsed,
+            level,
+            min_speed: config.min_speed * speed,
+            max_speed: config.max_speed * speed,
+            max_drift: max_drift * (1. + config.drift_per_level * level),
+            spawn_interval: config.spawn_interval / (1. + config.density_per_level * level),
+            since_spawn: 0.,
+        }
+    }
+}
+
+pub fn adaptive_difficulty(config: Res<GameConfig>) -> bool {
+    config.difficulty.mode == DifficultyMode::Adaptive
+}
+
+pub fn wave_difficulty(config: Res<GameConfig>) -> bool {
+    config.difficulty.mode == DifficultyMode::Waves
+}
+
+pub fn update_difficulty_curve(
+    time: Res<Time>,
+    score: Res<Score>,
+    config: Res<GameConfig>,
+    mut curve: ResMut<DifficultyCurve>,
+) {
+    let elapsed = curve.elapsed + time.delta_seconds();
+    let previous_level = curve.level.floor();
+    *curve = DifficultyCurve {
+        since_spawn: curve.since_spawn,
+        ..DifficultyCurve::evaluate(
+            &config.difficulty,
+            config.asteroids.max_drift,
+            elapsed,
+            score.0,
+        )
+    };
+    if curve.level.floor() > previous_level {
+        info!(
+            "difficulty level {:.0}: speed {:.0}-{:.0}, drift {:.0}, spawn every {:.2}s",
+            curve.level.floor(),
+            curve.min_speed,
+            curve.max_speed,
+            curve.max_drift,
+            curve.spawn_interval
+        );
+    }
+}
+
+/// Endless stream of asteroids paced by the difficulty curve.
+pub fn spawn_endless_asteroids(
+    mut commands: Commands,
+    mut curve: ResMut<DifficultyCurve>,
+    win_query: Query<&Window, With<PrimaryWindow>>,
+    game_assets: Res<GameAssets>,
+    config: Res<GameConfig>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    let Ok(win) = win_query.get_single() else {
+        return;
+    };
+    curve.since_spawn += time.delta_seconds();
+    while curve.since_spawn >= curve.spawn_interval {
+        curve.since_spawn -= curve.spawn_interval;
+        let velocity = Velocity {
+            speed: rng.gen_range(curve.min_speed..curve.max_speed),
+            direction_x: rng.gen_range(-curve.max_drift..curve.max_drift),
+        };
+        spawn_falling_asteroid(
+            &mut commands,
+            &config.asteroids,
+            pick_size(&config.difficulty.size_mix, rng.as_mut()),
+            game_assets.asteroid.clone(),
+            win,
+            velocity,
+            rng.as_mut(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-4, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn level_ramps_with_time() {
+        let config = DifficultyConfig::default();
+        let start = DifficultyCurve::evaluate(&config, 40., 0., 0);
+        approx(start.level, 0.);
+        approx(start.min_speed, config.min_speed);
+
+        let curve = DifficultyCurve::evaluate(&config, 40., 2. * config.seconds_per_level, 0);
+        approx(curve.level, 2.);
+        let speed = 1. + 2. * config.speed_per_level;
+        approx(curve.min_speed, config.min_speed * speed);
+        approx(curve.max_speed, config.max_speed * speed);
+        approx(curve.max_drift, 40. * (1. + 2. * config.drift_per_level));
+        approx(
+            curve.spawn_interval,
+            config.spawn_interval / (1. + 2. * config.density_per_level),
+        );
+    }
+
+    #[test]
+    fn level_ramps_with_score() {
+        let config = DifficultyConfig::default();
+        let curve = DifficultyCurve::evaluate(&config, 40., 0., 3000);
+        approx(curve.level, 3000. / config.score_per_level);
+        let curve = DifficultyCurve::evaluate(&config, 40., config.seconds_per_level, 2000);
+        approx(curve.level, 2.);
+        approx(DifficultyCurve::evaluate(&config, 40., 0., -500).level, 0.);
+    }
+
+    #[test]
+    fn level_stops_at_max_level() {
+        let config = DifficultyConfig::default();
+        let curve = DifficultyCurve::evaluate(&config, 40., 3600., 1_000_000);
+        approx(curve.level, config.max_level);
+        approx(
+            curve.min_speed,
+            config.min_speed * (1. + config.max_level * config.speed_per_level),
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use bevy::{audio::Volume, prelude::*};
+
+use crate::{AsteroidDestroyed, BossDefeated, EnemyDestroyed, GameConfig, InGame};
+
+const EXPLOSION_DURATION: f32 = 0.3;
+
+#[derive(Component)]
+pub struct Explosion {
+    timer: Timer,
+}
+
+pub fn play_explosion_sound(
+    mut commands: Commands,
+    mut asteroids_destroyed: EventReader<AsteroidDestroyed>,
+    mut enemies_destroyed: EventReader<EnemyDestroyed>,
+    mut bosses_defeated: EventReader<BossDefeated>,
+    assets: Res<AssetServer>,
+) {
+    let explosions = asteroids_destroyed.read().count()
+        + enemies_destroyed.read().count()
+        + bosses_defeated.read().count();
+    for _ in 0..explosions {
+        commands.spawn(AudioBundle {
+            source: assets.load("Explosion.ogg"),
+            settings: PlaybackSettings {
+                volume: Volume::new(0.1),
+                ..default()
+            },
+        });
+    }
+}
+
+/// Leaves a flash where an asteroid, enemy or boss was destroyed, sized like it.
+pub fn spawn_explosions(
+    mut commands: Commands,
+    mut asteroids_destroyed: EventReader<AsteroidDestroyed>,
+    mut enemies_destroyed: EventReader<EnemyDestroyed>,
+    mut bosses_defeated: EventReader<BossDefeated>,
+    config: Res<GameConfig>,
+) {
+    let asteroids = asteroids_destroyed
+        .read()
+        .map(|event| (event.translation, config.asteroids.get(event.size).size));
+    let enemies = enemies_destroyed
+        .read()
+        .map(|event| (event.translation, config.enemies.get(event.kind).size));
+    let bosses = bosses_defeated
+        .read()
+        .map(|event| (event.translation, event.size));
+    for (translation, size) in asteroids.chain(enemies).chain(bosses) {
+        commands.spawn((
+            SpriteBundle {
+                transform: Transform::from_translation(translation),
+                sprite: Sprite {
+                    color: Color::srgba(1., 0.6, 0.2, 0.8),
+                    custom_size: Some(Vec2::splat(size)),
+                    ..default()
+                },
+                ..default()
+            },
+            Explosion {
+                timer: Timer::from_seconds(EXPLOSION_DURATION, TimerMode::Once),
+            },
+            StateScoped(InGame),
+        ));
+    }
+}
+
+pub fn update_explosions(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Explosion, &mut Transform, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut explosion, mut transform, mut sprite) in query.iter_mut() {
+        explosion.timer.tick(time.delta());
+        let progress = explosion.timer.fraction();
+        transform.scale = Vec3::splat(1. + progress);
+        sprite.color.set_alpha(0.8 * (1. - progress));
+        if explosion.timer.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,400 @@
+use std::f32::consts::PI;
+
+use bevy::{math::vec3, prelude::*, utils::HashSet, window::PrimaryWindow};
+use rand::Rng;
+
+use crate::{
+    bosses::Boss, nearest_player, spaceship_collider, spawn_bullet, ActivePlayer, Bullet,
+    BulletHitEnemy, BulletHitPlayer, Collision, CollisionLayers, EnemyConfig, Faction, GameAssets,
+    GameConfig, GameRng, InGame, Player, PlayerHitEnemy,
+};
+
+/// Fraction of the window height, from the top, where strafers settle and divers start diving.
+const HOLD_HEIGHT: f32 = 0.3;
+const DIVE_SPEED_FACTOR: f32 = 2.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnemyKind {
+    /// Settles near the top and sweeps from side to side.
+    Strafer,
+    /// Drops in, then dives at where the player was.
+    Diver,
+    /// Flies down in a sine-wave formation.
+    Weaver,
+}
+
+impl EnemyKind {
+    pub const ALL: [EnemyKind; 3] = [EnemyKind::Strafer, EnemyKind::Diver, EnemyKind::Weaver];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EnemyKind::Strafer => "strafer",
+            EnemyKind::Diver => "diver",
+            EnemyKind::Weaver => "weaver",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            EnemyKind::Strafer => Color::srgb(1., 0.5, 0.5),
+            EnemyKind::Diver => Color::srgb(1., 0.8, 0.3),
+            EnemyKind::Weaver => Color::srgb(0.6, 0.6, 1.),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Enemy {
+    pub kind: EnemyKind,
+    pub health: u32,
+    pub fire_timer: Timer,
+}
+
+/// Filter for enemy ships and bosses, everything the player shoots at besides asteroids.
+pub type Hostile = Or<(With<Enemy>, With<Boss>)>;
+
+#[derive(Component)]
+pub enum EnemyBehaviour {
+    Strafe { direction: f32 },
+    Dive { velocity: Option<Vec2> },
+    Weave { origin_x: f32, phase: f32, age: f32 },
+}
+
+#[derive(Event, Clone, Copy)]
+pub struct EnemyDestroyed {
+    pub enemy: Entity,
+    pub kind: EnemyKind,
+    pub translation: Vec3,
+    /// Player who shot or rammed the enemy.
+    pub destroyed_by: Option<Entity>,
+}
+
+#[derive(Resource)]
+pub struct EnemySpawner {
+    timer: Timer,
+}
+
+impl EnemySpawner {
+    pub fn new(config: &EnemyConfig) -> Self {
+        Self {
+            timer: Timer::from_seconds(config.first_spawn_delay.max(0.), TimerMode::Once),
+        }
+    }
+}
+
+fn spawn_enemy(
+    commands: &mut Commands,
+    config: &EnemyConfig,
+    kind: EnemyKind,
+    behaviour: EnemyBehaviour,
+    texture: Handle<Image>,
+    translation: Vec3,
+) {
+    let enemy_config = config.get(kind);
+    commands.spawn((
+        SpriteBundle {
+            transform: Transform {
+                translation,
+                rotation: Quat::from_rotation_z(PI),
+                ..default()
+            },
+            sprite: Sprite {
+                color: kind.color(),
+                custom_size: Some(Vec2::splat(enemy_config.size)),
+                ..default()
+            },
+            texture,
+            ..default()
+        },
+        Enemy {
+            kind,
+            health: enemy_config.health,
+            fire_timer: Timer::from_seconds(enemy_config.fire_interval, TimerMode::Repeating),
+        },
+        behaviour,
+        spaceship_collider(enemy_config.size),
+        CollisionLayers::new(
+            CollisionLayers::ENEMY,
+            CollisionLayers::PLAYER | CollisionLayers::BULLET,
+        ),
+        StateScoped(InGame),
+    ));
+}
+
+pub fn spawn_enemies(
+    mut commands: Commands,
+    mut spawner: ResMut<EnemySpawner>,
+    win_query: Query<&Window, With<PrimaryWindow>>,
+    game_assets: Res<GameAssets>,
+    config: Res<GameConfig>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    let Ok(win) = win_query.get_single() else {
+        return;
+    };
+    if !spawner.timer.tick(time.delta()).finished() {
+        return;
+    }
+    let config = &config.enemies;
+    spawner.timer = Timer::from_seconds(config.spawn_interval, TimerMode::Once);
+
+    let kind = EnemyKind::ALL[rng.gen_range(0..EnemyKind::ALL.len())];
+    let size = config.get(kind).size;
+    let half_width = (win.width() / 2. - size).max(1.);
+    let y = win.height() / 2. + size;
+    match kind {
+        EnemyKind::Strafer | EnemyKind::Diver => {
+            let behaviour = if kind == EnemyKind::Strafer {
+                EnemyBehaviour::Strafe {
+                    direction: if rng.gen_bool(0.5) { 1. } else { -1. },
+                }
+            } else {
+                EnemyBehaviour::Dive { velocity: None }
+            };
+            let x = rng.gen_range(-half_width..half_width);
+            spawn_enemy(
+                &mut commands,
+                config,
+                kind,
+                behaviour,
+                game_assets.space_ship.clone(),
+                vec3(x, y, 0.),
+            );
+        }
+        EnemyKind::Weaver => {
+            let spacing = size * 1.5;
+            let row_width = spacing * (config.formation_size - 1) as f32;
+            let max_x = (half_width - config.weave_amplitude - row_width / 2.).max(0.);
+            let center = rng.gen_range(-max_x..=max_x);
+            for i in 0..config.formation_size {
+                let origin_x = center - row_width / 2. + spacing * i as f32;
+                spawn_enemy(
+                    &mut commands,
+                    config,
+                    kind,
+                    EnemyBehaviour::Weave {
+                        origin_x,
+                        phase: i as f32 * 0.5,
+                        age: 0.,
+                    },
+                    game_assets.space_ship.clone(),
+                    vec3(origin_x, y, 0.),
+                );
+            }
+        }
+    }
+}
+
+pub fn move_enemies(
+    mut enemies_query: Query<(&Enemy, &mut EnemyBehaviour, &mut Transform)>,
+    player_query: Query<&Transform, (ActivePlayer, Without<Enemy>)>,
+    win_query: Query<&Window, With<PrimaryWindow>>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+) {
+    let Ok(win) = win_query.get_single() else {
+        return;
+    };
+    let dt = time.delta_seconds();
+    let hold_y = win.height() / 2. - win.height() * HOLD_HEIGHT;
+    for (enemy, mut behaviour, mut transform) in enemies_query.iter_mut() {
+        let enemy_config = config.enemies.get(enemy.kind);
+        let speed = enemy_config.speed;
+        match behaviour.as_mut() {
+            EnemyBehaviour::Strafe { direction } => {
+                if transform.translation.y > hold_y {
+                    transform.translation.y -= speed * dt;
+                } else {
+                    transform.translation.x += *direction * speed * dt;
+                    let edge = win.width() / 2. - enemy_config.size / 2.;
+                    if transform.translation.x.abs() > edge {
+                        transform.translation.x = transform.translation.x.clamp(-edge, edge);
+                        *direction = -*direction;
+                    }
+                }
+            }
+            EnemyBehaviour::Dive { velocity } => match velocity {
+                Some(velocity) => transform.translation += (*velocity * dt).extend(0.),
+                None if transform.translation.y > hold_y => {
+                    transform.translation.y -= speed * dt;
+                }
+                None => {
+                    let target = nearest_player(&player_query, transform.translation.truncate())
+                        .unwrap_or(Vec2::new(transform.translation.x, -win.height()));
+                    let direction =
+                        (target - transform.translation.truncate()).normalize_or(Vec2::NEG_Y);
+                    *velocity = Some(direction * speed * DIVE_SPEED_FACTOR);
+                }
+            },
+            EnemyBehaviour::Weave {
+                origin_x,
+                phase,
+                age,
+            } => {
+                *age += dt;
+                transform.translation.y -= speed * dt;
+                transform.translation.x = *origin_x
+                    + config.enemies.weave_amplitude
+                        * (*age * config.enemies.weave_frequency + *phase).sin();
+            }
+        }
+    }
+}
+
+/// Strafers and divers aim at the nearest player, weavers fire straight down.
+pub fn enemy_fire(
+    mut commands: Commands,
+    mut enemies_query: Query<(&mut Enemy, &Transform)>,
+    player_query: Query<&Transform, ActivePlayer>,
+    win_query: Query<&Window, With<PrimaryWindow>>,
+    assets: Res<AssetServer>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+) {
+    let Ok(win) = win_query.get_single() else {
+        return;
+    };
+    for (mut enemy, transform) in enemies_query.iter_mut() {
+        if !enemy.fire_timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let position = transform.translation;
+        // Only shoot once fully on screen.
+        if position.y > win.height() / 2. {
+            continue;
+        }
+        let player = nearest_player(&player_query, position.truncate());
+        let direction = match (enemy.kind, player) {
+            (EnemyKind::Strafer | EnemyKind::Diver, Some(player)) => {
+                (player - position.truncate()).normalize_or(Vec2::NEG_Y)
+            }
+            _ => Vec2::NEG_Y,
+        };
+        spawn_bullet(
+            &mut commands,
+            Bullet::new(Faction::Enemy, direction * config.enemies.bullet_speed),
+            assets.load("Spritesheet/fire.png"),
+            position.with_z(-1.),
+            Vec2::splat(config.bullet.size),
+        );
+    }
+}
+
+pub fn despawn_escaped_enemies(
+    mut commands: Commands,
+    query: Query<(Entity, &Transform, &Enemy)>,
+    win_query: Query<&Window, With<PrimaryWindow>>,
+    config: Res<GameConfig>,
+) {
+    if let Ok(win) = win_query.get_single() {
+        for (entity, transform, enemy) in query.iter() {
+            let margin = config.enemies.get(enemy.kind).size;
+            let position = transform.translation;
+            if position.y < -win.height() / 2. - margin
+                || position.x.abs() > win.width() / 2. + margin
+            {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+/// Player bullets hit enemies and bosses, enemy bullets hit the player and ramming hits both.
+/// Runs after [`detect_bullet_collision`](crate::detect_bullet_collision), so a non-piercing
+/// bullet already spent on an asteroid this frame is not counted again.
+pub fn detect_enemy_collision(
+    mut collisions: EventReader<Collision>,
+    mut bullets_query: Query<&mut Bullet>,
+    enemies_query: Query<(), Hostile>,
+    player_query: Query<(), With<Player>>,
+    mut bullet_hits: EventWriter<BulletHitEnemy>,
+    mut player_hits: EventWriter<BulletHitPlayer>,
+    mut rams: EventWriter<PlayerHitEnemy>,
+) {
+    let mut handled = HashSet::new();
+    for collision in collisions.read() {
+        if let Some((bullet, other)) = collision.ordered_by(|e| bullets_query.contains(e)) {
+            if handled.contains(&bullet) {
+                continue;
+            }
+            let Ok(mut state) = bullets_query.get_mut(bullet) else {
+                continue;
+            };
+            match state.faction {
+                Faction::Player if enemies_query.contains(other) => {
+                    if (!state.piercing && !state.hits.is_empty()) || !state.hits.insert(other) {
+                        continue;
+                    }
+                    if !state.piercing {
+                        handled.insert(bullet);
+                    }
+                    bullet_hits.send(BulletHitEnemy {
+                        bullet,
+                        enemy: other,
+                        damage: state.damage,
+                        owner: state.owner,
+                    });
+                }
+                Faction::Enemy if player_query.contains(other) => {
+                    handled.insert(bullet);
+                    player_hits.send(BulletHitPlayer {
+                        bullet,
+                        player: other,
+                    });
+                }
+                _ => {}
+            }
+        } else if let Some((player, enemy)) = collision.ordered_by(|e| player_query.contains(e)) {
+            if enemies_query.contains(enemy) && handled.insert(enemy) {
+                rams.send(PlayerHitEnemy { player, enemy });
+            }
+        }
+    }
+}
+
+pub fn damage_enemies(
+    mut bullet_hits: EventReader<BulletHitEnemy>,
+    mut rams: EventReader<PlayerHitEnemy>,
+    mut enemies_query: Query<(&mut Enemy, &Transform)>,
+    mut destroyed: EventWriter<EnemyDestroyed>,
+) {
+    for hit in bullet_hits.read() {
+        if let Ok((mut enemy, transform)) = enemies_query.get_mut(hit.enemy) {
+            if enemy.health == 0 {
+                continue;
+            }
+            enemy.health = enemy.health.saturating_sub(hit.damage);
+            if enemy.health == 0 {
+                destroyed.send(EnemyDestroyed {
+                    enemy: hit.enemy,
+                    kind: enemy.kind,
+                    translation: transform.translation,
+                    destroyed_by: hit.owner,
+                });
+            }
+        }
+    }
+    for ram in rams.read() {
+        if let Ok((mut enemy, transform)) = enemies_query.get_mut(ram.enemy) {
+            if enemy.health > 0 {
+                enemy.health = 0;
+                destroyed.send(EnemyDestroyed {
+                    enemy: ram.enemy,
+                    kind: enemy.kind,
+                    translation: transform.translation,
+                    destroyed_by: Some(ram.player),
+                });
+            }
+        }
+    }
+}
+
+pub fn despawn_destroyed_enemies(
+    mut commands: Commands,
+    mut destroyed: EventReader<EnemyDestroyed>,
+) {
+    for event in destroyed.read() {
+        commands.entity(event.enemy).despawn();
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{load_ron, save_ron, Score};
+
+pub const MAX_HIGH_SCORES: usize = 10;
+const MAX_NAME_LENGTH: usize = 12;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HighScoreEntry {
+    pub name: String,
+    pub score: i64,
+}
+
+#[derive(Resource, Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct HighScores {
+    pub entries: Vec<HighScoreEntry>,
+}
+
+impl HighScores {
+    pub fn qualifies(&self, score: i64) -> bool {
+        score > 0
+            && (self.entries.len() < MAX_HIGH_SCORES
+                || self.entries.iter().any(|entry| score > entry.score))
+    }
+
+    /// Inserts a score keeping the table sorted and capped, returns its rank if it made the cut.
+    pub fn insert(&mut self, name: &str, score: i64) -> Option<usize> {
+        if !self.qualifies(score) {
+            return None;
+        }
+        let rank = self
+            .entries
+            .iter()
+            .position(|entry| score > entry.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(
+            rank,
+            HighScoreEntry {
+                name: name.to_string(),
+                score,
+            },
+        );
+        self.entries.truncate(MAX_HIGH_SCORES);
+        Some(rank)
+    }
+
+    /// Reads the table from `path`. A missing or unreadable file yields an empty table.
+    pub fn load(path: &Path) -> Self {
+        let Some(mut high_scores) = load_ron::<HighScores>(path, "high scores") else {
+            return Self::default();
+        };
+        high_scores
+            .entries
+            .sort_by_key(|entry| std::cmp::Reverse(entry.score));
+        high_scores.entries.truncate(MAX_HIGH_SCORES);
+        high_scores
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        save_ron(path, self, true)
+    }
+}
+
+pub fn high_scores_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("spaceship-game").join("highscores.ron"))
+}
+
+pub fn load_high_scores(mut commands: Commands) {
+    let high_scores = high_scores_path()
+        .map(|path| HighScores::load(&path))
+        .unwrap_or_default();
+    commands.insert_resource(high_scores);
+}
+
+#[derive(Component, Default)]
+pub struct NameEntry {
+    pub name: String,
+    pub submitted: bool,
+}
+
+pub fn update_name_entry(
+    mut events: EventReader<KeyboardInput>,
+    mut name_entry_query: Query<(&mut NameEntry, &mut Text)>,
+    mut high_scores: ResMut<HighScores>,
+    score: Res<Score>,
+) {
+    let Ok((mut entry, mut text)) = name_entry_query.get_single_mut() else {
+        return;
+    };
+    // Keys still held from gameplay shouldn't end up in the name.
+    if entry.is_added() || entry.submitted {
+        events.clear();
+        return;
+    }
+    for event in events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(chars) => {
+                for c in chars.chars().filter(|c| !c.is_control()) {
+                    if entry.name.chars().count() < MAX_NAME_LENGTH {
+                        entry.name.push(c);
+                    }
+                }
+            }
+            Key::Space if entry.name.chars().count() < MAX_NAME_LENGTH => {
+                entry.name.push(' ');
+            }
+            Key::Backspace => {
+                entry.name.pop();
+            }
+            Key::Enter if !entry.name.trim().is_empty() => {
+                entry.submitted = true;
+                high_scores.insert(entry.name.trim(), score.0);
+                if let Some(path) = high_scores_path() {
+                    if let Err(err) = high_scores.save(&path) {
+                        warn!("could not save high scores to {}: {err}", path.display());
+                    }
+                }
+                break;
+            }
+            _ => {}
+        }
+    }
+    text.sections[0].value = if entry.submitted {
+        format!("Saved as {}", entry.name.trim())
+    } else {
+        format!("Name: {}_", entry.name)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(scores: &[i64]) -> HighScores {
+        let mut high_scores = HighScores::default();
+        for score in scores {
+            high_scores.insert("AAA", *score);
+        }
+        high_scores
+    }
+
+    #[test]
+    fn insert_keeps_entries_sorted_and_capped() {
+        let mut high_scores = table(&[300, 100, 200]);
+        let scores: Vec<_> = high_scores.entries.iter().map(|e| e.score).collect();
+        assert_eq!(scores, vec![300, 200, 100]);
+
+        for score in 1..=20 {
+            high_scores.insert("BBB", score * 1000);
+        }
+        assert_eq!(high_scores.entries.len(), MAX_HIGH_SCORES);
+        assert_eq!(high_scores.entries[0].score, 20000);
+        assert_eq!(high_scores.entries[9].score, 11000);
+    }
+
+    #[test]
+    fn qualifies_only_beats_the_lowest_of_a_full_table() {
+        let high_scores = table(&[100, 200, 300, 400, 500, 600, 700, 800, 900, 1000]);
+        assert!(!high_scores.qualifies(100));
+        assert!(high_scores.qualifies(101));
+        assert!(!HighScores::default().qualifies(0));
+        assert_eq!(table(&[200]).insert("CCC", 100), Some(1));
+    }
+
+    #[test]
+    fn saved_table_loads_back() {
+        crate::assert_persists(
+            "highscores",
+            table(&[500, 1500, 1000]),
+            HighScores::default(),
+            HighScores::save,
+            HighScores::load,
+        );
+    }
+}
@@ -1,13 +1,55 @@
 use bevy::{
     math::{vec2, vec3},
     prelude::*,
+    sprite::Anchor,
+    utils::HashSet,
     window::{PrimaryWindow, WindowResized},
 };
 
-use crate::Player;
+use crate::{
+    Boss, BossDefinition, GameAssets, GameConfig, Health, InGame, Player, PlayerCount, PlayerIndex,
+    PlayerScores, PowerUpKind, PowerUps, Score, WaveEvent, WeaponConfig,
+};
+
+const WAVE_BANNER_DURATION: f32 = 2.0;
+
+/// HUD element showing the state of one player. The first player's elements sit on the
+/// right, the second player's are mirrored to the left.
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerHud(pub usize);
+
+impl PlayerHud {
+    /// 1 on the first player's side of the screen, -1 on the second's.
+    fn side(self) -> f32 {
+        if self.0 == 0 {
+            1.
+        } else {
+            -1.
+        }
+    }
+
+    /// Absolutely positioned style `inset` pixels from the bottom corner on this side.
+    fn bottom_corner(self, bottom: f32, inset: f32) -> Style {
+        let mut style = Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(bottom),
+            ..default()
+        };
+        if self.0 == 0 {
+            style.right = Val::Px(inset);
+        } else {
+            style.left = Val::Px(inset);
+        }
+        style
+    }
+}
 
 #[derive(Component)]
-pub struct ReLoadingText;
+pub struct ReloadBar;
+
+#[derive(Component)]
+pub struct ReloadFill;
+
 #[derive(Component)]
 pub struct AmmoText;
 
@@ -15,23 +57,42 @@ pub struct AmmoText;
 pub struct AmmoIcon;
 
 #[derive(Component)]
-pub struct Heart;
+pub struct WeaponText;
 
 #[derive(Component)]
-pub struct GameOverButton;
+pub struct Heart {
+    player: usize,
+    index: u32,
+}
 
 #[derive(Component)]
-pub struct GameOverText;
+pub struct ScoreText;
 
 #[derive(Component)]
-pub struct ScoreText;
+pub struct PowerUpText;
 
-pub fn spawn_ammo_icon(commands: &mut Commands, texture: Handle<Image>, win: &Window) {
+#[derive(Component)]
+pub struct BossHealthBar;
+
+#[derive(Component)]
+pub struct BossHealthFill;
+
+#[derive(Component)]
+pub struct WaveBanner {
+    timer: Timer,
+}
+
+pub fn spawn_ammo_icon(
+    commands: &mut Commands,
+    texture: Handle<Image>,
+    win: &Window,
+    hud: PlayerHud,
+) {
     commands.spawn((
         SpriteBundle {
             transform: Transform {
                 translation: vec3(
-                    win.resolution.width() / 2. - 25.0,
+                    hud.side() * (win.resolution.width() / 2. - 90.0),
                     -(win.resolution.height() / 2.) - 25.0,
                     0.0,
                 ),
@@ -45,245 +106,261 @@ pub fn spawn_ammo_icon(commands: &mut Commands, texture: Handle<Image>, win: &Wi
             ..default()
         },
         AmmoIcon,
+        hud,
+        StateScoped(InGame),
     ));
 }
 
 pub fn update_ammo_icon_pos(
     win_query: Query<&Window, With<PrimaryWindow>>,
-    mut query: Query<&mut Transform, With<AmmoIcon>>,
+    mut query: Query<(&mut Transform, &PlayerHud), With<AmmoIcon>>,
     events: EventReader<WindowResized>,
 ) {
     if let Ok(win) = win_query.get_single() {
-        if let Ok(mut transform) = query.get_single_mut() {
-            if !events.is_empty() {
-                transform.translation.x = win.resolution.width() / 2. - 90.0;
-                transform.translation.y = -win.resolution.height() / 2. + 30.0;
-            }
+        if events.is_empty() {
+            return;
+        }
+        for (mut transform, hud) in query.iter_mut() {
+            transform.translation.x = hud.side() * (win.resolution.width() / 2. - 90.0);
+            transform.translation.y = -win.resolution.height() / 2. + 30.0;
         }
     }
 }
 
-pub fn spawn_ammo_text(commands: &mut Commands) {
+pub fn update_ammo_icon_color(
+    player_query: Query<(&Player, &PlayerIndex), Changed<Player>>,
+    mut query: Query<(&mut Sprite, &PlayerHud), With<AmmoIcon>>,
+) {
+    for (player, index) in player_query.iter() {
+        for (mut sprite, _) in query.iter_mut().filter(|(_, hud)| hud.0 == index.0) {
+            sprite.color = player.weapon().stats.pattern.color();
+        }
+    }
+}
+
+pub fn spawn_ammo_text(commands: &mut Commands, weapon: &WeaponConfig, hud: PlayerHud) {
     commands.spawn((
         TextBundle::from(TextSection::new(
-            "60",
+            format!("{}", weapon.magazine),
             TextStyle {
                 font_size: 40.0,
                 ..default()
             },
         ))
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-            bottom: Val::Px(5.0),
-            right: Val::Px(15.0),
-            ..default()
-        }),
+        .with_style(hud.bottom_corner(5.0, 15.0)),
         AmmoText,
+        hud,
+        StateScoped(InGame),
     ));
 }
 
 pub fn update_ammo_text(
-    player_query: Query<&Player, With<Player>>,
-    mut ammo_text_query: Query<&mut Text, With<AmmoText>>,
+    player_query: Query<(&Player, &PlayerIndex)>,
+    mut ammo_text_query: Query<(&mut Text, &PlayerHud), With<AmmoText>>,
 ) {
-    if let Ok(mut ammo_text) = ammo_text_query.get_single_mut() {
-        if let Ok(player) = player_query.get_single() {
-            let ammo = player.ammunition;
+    for (mut ammo_text, hud) in ammo_text_query.iter_mut() {
+        if let Some((player, _)) = player_query.iter().find(|(_, index)| index.0 == hud.0) {
+            let ammo = player.weapon().ammunition;
             ammo_text.sections[0].value = {
                 if ammo < 10 {
-                    format!("0{}", player.ammunition)
+                    format!("0{}", ammo)
                 } else {
-                    format!("{}", player.ammunition)
+                    format!("{}", ammo)
                 }
             }
         }
     }
 }
 
-pub fn spawn_hearts(commands: &mut Commands, texture: Handle<Image>, win: &Window) {
-    let height = win.resolution.height();
-    let width = win.resolution.width();
-    let top_left_x = -width / 2.0 + 50.0;
-    let top_left_y = height / 2.0;
-    for i in 0..3 {
-        commands.spawn((
-            SpriteBundle {
-                transform: Transform {
-                    translation: vec3(top_left_x + (i as f32 * 60.0), top_left_y, 0.0),
-                    ..default()
-                },
-                sprite: Sprite {
-                    custom_size: Some(vec2(50.0, 50.0)),
-                    ..default()
-                },
-                texture: texture.clone(),
+/// Weapon name, followed by the spare rounds for weapons with a limited reserve.
+fn weapon_label(name: &str, reserve: Option<usize>) -> String {
+    match reserve {
+        Some(reserve) => format!("{name} ({reserve})"),
+        None => name.to_string(),
+    }
+}
+
+pub fn spawn_weapon_text(commands: &mut Commands, weapon: &WeaponConfig, hud: PlayerHud) {
+    commands.spawn((
+        TextBundle::from(TextSection::new(
+            weapon_label(&weapon.name, weapon.reserve),
+            TextStyle {
+                font_size: 30.0,
+                color: weapon.pattern.color(),
                 ..default()
             },
-            Heart,
-        ));
-    }
+        ))
+        .with_style(hud.bottom_corner(55.0, 15.0)),
+        WeaponText,
+        hud,
+        StateScoped(InGame),
+    ));
 }
-pub fn update_hearts_pos(
-    win_query: Query<&Window, With<PrimaryWindow>>,
-    mut hearts_query: Query<&mut Transform, With<Heart>>,
+
+pub fn update_weapon_text(
+    player_query: Query<(&Player, &PlayerIndex), Changed<Player>>,
+    mut text_query: Query<(&mut Text, &PlayerHud), With<WeaponText>>,
 ) {
-    if let Ok(win) = win_query.get_single() {
-        let width = win.resolution.width();
-        let height = win.resolution.height();
-        let top_left_x = -width / 2.0 + 30.0;
-        let top_left_y = height / 2.0 - 40.;
-        for (idx, mut heart) in hearts_query.iter_mut().enumerate() {
-            heart.translation.x = top_left_x + (idx as f32 * 60.0);
-            heart.translation.y = top_left_y;
+    for (player, index) in player_query.iter() {
+        for (mut text, _) in text_query.iter_mut().filter(|(_, hud)| hud.0 == index.0) {
+            let weapon = player.weapon();
+            text.sections[0].value = weapon_label(&weapon.stats.name, weapon.reserve);
+            text.sections[0].style.color = weapon.stats.pattern.color();
         }
     }
 }
 
-pub fn spawn_reloading_text(commands: &mut Commands) {
-    let mut text_bundle = TextBundle::from(TextSection::new(
-        "Reloading",
-        TextStyle {
-            font_size: 40.0,
+const HEART_SIZE: f32 = 50.0;
+
+/// Sprite for a heart holding `filled` half hearts, the texture size is needed to cut it in half.
+fn heart_sprite(filled: u32, texture_size: Option<Vec2>) -> Sprite {
+    match (filled, texture_size) {
+        (0, _) => Sprite {
+            color: Color::srgba(0.3, 0.3, 0.3, 0.5),
+            custom_size: Some(Vec2::splat(HEART_SIZE)),
+            ..default()
+        },
+        (1, Some(size)) => Sprite {
+            custom_size: Some(vec2(HEART_SIZE / 2., HEART_SIZE)),
+            rect: Some(Rect::new(0., 0., size.x / 2., size.y)),
+            // Keeps the left half where it would be on a full heart.
+            anchor: Anchor::CenterRight,
             ..default()
         },
-    ))
-    .with_style(Style {
-        position_type: PositionType::Absolute,
-        bottom: Val::Px(5.0),
-        right: Val::Px(130.0),
-        ..default()
-    });
-    text_bundle.visibility = Visibility::Hidden;
-    commands.spawn((text_bundle, ReLoadingText));
+        _ => Sprite {
+            custom_size: Some(Vec2::splat(HEART_SIZE)),
+            ..default()
+        },
+    }
 }
 
-pub fn update_reloading_text(
-    player_query: Query<&Player, With<Player>>,
-    mut reloading_text_query: Query<&mut Visibility, With<ReLoadingText>>,
+/// Keeps one icon per heart of each player's max health, each showing full, half or empty.
+/// The first player's hearts run from the top left, the second player's from the top right,
+/// below the score.
+pub fn update_hearts(
+    mut commands: Commands,
+    player_query: Query<(&Health, &PlayerIndex)>,
+    mut hearts_query: Query<(Entity, &Heart, &mut Sprite, &mut Transform)>,
+    win_query: Query<&Window, With<PrimaryWindow>>,
+    game_assets: Res<GameAssets>,
+    images: Res<Assets<Image>>,
 ) {
-    if let Ok(player) = player_query.get_single() {
-        if let Ok(mut reloading_text_visibility) = reloading_text_query.get_single_mut() {
-            if player.ammunition == 0 {
-                *reloading_text_visibility = Visibility::Visible;
-            } else {
-                *reloading_text_visibility = Visibility::Hidden;
+    if let Ok(win) = win_query.get_single() {
+        let texture_size = images.get(&game_assets.heart).map(|image| image.size_f32());
+        for (health, player) in player_query.iter() {
+            let count = health.max.div_ceil(2);
+            let side = PlayerHud(player.0).side();
+            let first_x = -side * (win.resolution.width() / 2.0 - 30.0);
+            let top_y = win.resolution.height() / 2.0 - 40. - 60. * player.0 as f32;
+            let position = |index: u32| vec3(first_x + side * (index as f32 * 60.0), top_y, 0.0);
+            let filled = |index: u32| health.current.saturating_sub(index * 2).min(2);
+
+            let mut shown = HashSet::new();
+            for (entity, heart, mut sprite, mut transform) in hearts_query
+                .iter_mut()
+                .filter(|(_, heart, _, _)| heart.player == player.0)
+            {
+                if heart.index >= count {
+                    commands.entity(entity).despawn();
+                    continue;
+                }
+                shown.insert(heart.index);
+                transform.translation = position(heart.index);
+                *sprite = heart_sprite(filled(heart.index), texture_size);
+            }
+            for index in (0..count).filter(|index| !shown.contains(index)) {
+                commands.spawn((
+                    SpriteBundle {
+                        transform: Transform::from_translation(position(index)),
+                        sprite: heart_sprite(filled(index), texture_size),
+                        texture: game_assets.heart.clone(),
+                        ..default()
+                    },
+                    Heart {
+                        player: player.0,
+                        index,
+                    },
+                    StateScoped(InGame),
+                ));
             }
         }
     }
 }
 
-pub fn spawn_game_over_text(commands: &mut Commands) {
-    let mut text_bundle = TextBundle::from(TextSection::new(
-        "Game Over",
-        TextStyle {
-            font_size: 40.0,
-            ..default()
-        },
-    ))
-    .with_style(Style {
-        position_type: PositionType::Absolute,
-        align_self: AlignSelf::Center,
-        top: Val::Percent(40.0),
-        margin: UiRect {
-            left: Val::Auto,
-            right: Val::Auto,
-            top: Val::Auto,
-            bottom: Val::Auto,
-        },
-        ..default()
-    });
-    text_bundle.visibility = Visibility::Hidden;
-    commands.spawn((text_bundle, GameOverText));
-}
-
-pub fn spawn_restart_button(commands: &mut Commands) {
+pub fn spawn_reload_bar(commands: &mut Commands, hud: PlayerHud) {
     commands
-        .spawn(ButtonBundle {
-            style: Style {
-                width: Val::Auto,
-                height: Val::Auto,
-                position_type: PositionType::Absolute,
-                align_self: AlignSelf::Center,
-                margin: UiRect::all(Val::Auto),
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Px(120.0),
+                    height: Val::Px(12.0),
+                    ..hud.bottom_corner(18.0, 130.0)
+                },
+                background_color: Color::srgba(1., 1., 1., 0.2).into(),
+                visibility: Visibility::Hidden,
                 ..default()
             },
-            background_color: BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
-            visibility: Visibility::Hidden, // Set visibility directly here
-            ..default()
-        })
-        .insert(GameOverButton) // Insert the `GameOverButton` component
+            ReloadBar,
+            hud,
+            StateScoped(InGame),
+        ))
         .with_children(|parent| {
-            parent.spawn(TextBundle {
-                text: Text::from_section(
-                    "Restart",
-                    TextStyle {
-                        font_size: 20.0,
-                        color: Color::WHITE,
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(0.),
+                        height: Val::Percent(100.),
                         ..default()
                     },
-                ),
-                ..default()
-            });
+                    background_color: Color::WHITE.into(),
+                    ..default()
+                },
+                ReloadFill,
+            ));
         });
 }
 
-pub fn update_game_over_button(
-    mut commands: Commands,
-    mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor, &Children),
-        (Changed<Interaction>, With<Button>),
-    >,
-    mut visibility_params_query: ParamSet<(
-        Query<&mut Visibility, With<GameOverText>>,
-        Query<&mut Visibility, With<GameOverButton>>,
-    )>,
-    win_query: Query<&Window, With<PrimaryWindow>>,
-    mut player_query: Query<&mut Transform, With<Player>>,
-    mut text_query: Query<&mut Text>,
-    assets: Res<AssetServer>,
+/// Shows how far the active weapon's reload has come, hidden while not reloading.
+pub fn update_reload_bar(
+    player_query: Query<(&Player, &PlayerIndex)>,
+    mut bar_query: Query<(&mut Visibility, &PlayerHud, &Children), With<ReloadBar>>,
+    mut fill_query: Query<&mut Style, With<ReloadFill>>,
 ) {
-    for (interaction, mut color, children) in &mut interaction_query {
-        let mut text = text_query.get_mut(children[0]).unwrap();
-        match *interaction {
-            Interaction::Pressed => {
-                if let Ok(mut game_over_text_visibility) =
-                    visibility_params_query.p0().get_single_mut()
-                {
-                    *game_over_text_visibility = Visibility::Hidden;
-                }
-                if let Ok(mut game_over_button_visibility) =
-                    visibility_params_query.p1().get_single_mut()
-                {
-                    *game_over_button_visibility = Visibility::Hidden;
-                }
-                let texture: Handle<Image> = assets.load("../assets/Spritesheet/heart.png");
-                if let Ok(win) = win_query.get_single() {
-                    spawn_hearts(&mut commands, texture, win);
-                    if let Ok(mut player) = player_query.get_single_mut() {
-                        player.translation.x = 0.;
-                    }
-                }
-            }
-            Interaction::Hovered => {
-                *color = BackgroundColor(Color::WHITE);
-                text.sections[0].style.color = Color::srgb(0., 0., 0.)
-            }
-            _ => {
-                *color = BackgroundColor(Color::srgb(0.2, 0.2, 0.2));
-                text.sections[0].style.color = Color::WHITE
+    for (mut visibility, hud, children) in bar_query.iter_mut() {
+        if let Some((player, _)) = player_query.iter().find(|(_, index)| index.0 == hud.0) {
+            let progress = player.weapon().reload_progress();
+            *visibility = if progress.is_some() {
+                Visibility::Visible
+            } else {
+                Visibility::Hidden
+            };
+            let mut fills = fill_query.iter_many_mut(children);
+            while let Some(mut style) = fills.fetch_next() {
+                style.width = Val::Percent(100. * progress.unwrap_or(0.));
             }
         }
     }
 }
+
 pub fn spawn_score_text(commands: &mut Commands) {
     commands.spawn((
-        TextBundle::from(TextSection::new(
-            "0",
-            TextStyle {
-                font_size: 40.0,
-                ..default()
-            },
-        ))
+        TextBundle::from_sections([
+            TextSection::new(
+                "0",
+                TextStyle {
+                    font_size: 40.0,
+                    ..default()
+                },
+            ),
+            // Each player's share, only filled in co-op.
+            TextSection::new(
+                "",
+                TextStyle {
+                    font_size: 20.0,
+                    ..default()
+                },
+            ),
+        ])
         .with_style(Style {
             position_type: PositionType::Absolute,
             top: Val::Px(5.0),
@@ -291,5 +368,244 @@ pub fn spawn_score_text(commands: &mut Commands) {
             ..default()
         }),
         ScoreText,
+        StateScoped(InGame),
     ));
 }
+
+/// "P1 120  P2 80", empty outside of co-op.
+pub fn score_split(player_scores: &PlayerScores, player_count: usize) -> String {
+    if player_count < 2 {
+        return String::new();
+    }
+    player_scores.0[..player_count]
+        .iter()
+        .enumerate()
+        .map(|(i, score)| format!("P{} {score}", i + 1))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+pub fn update_score_text(
+    score: Res<Score>,
+    player_scores: Res<PlayerScores>,
+    player_count: Res<PlayerCount>,
+    mut score_query: Query<&mut Text, With<ScoreText>>,
+) {
+    if let Ok(mut text) = score_query.get_single_mut() {
+        text.sections[0].value = format!("{}", score.0);
+        let split = score_split(&player_scores, player_count.0);
+        text.sections[1].value = if split.is_empty() {
+            split
+        } else {
+            format!("\n{split}")
+        };
+    }
+}
+
+pub fn spawn_powerup_text(commands: &mut Commands, hud: PlayerHud) {
+    commands.spawn((
+        TextBundle::from(TextSection::new(
+            "",
+            TextStyle {
+                font_size: 24.0,
+                ..default()
+            },
+        ))
+        .with_style(if hud.0 == 0 {
+            Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(75.0),
+                left: Val::Px(10.0),
+                ..default()
+            }
+        } else {
+            Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(135.0),
+                right: Val::Px(10.0),
+                ..default()
+            }
+        }),
+        PowerUpText,
+        hud,
+        StateScoped(InGame),
+    ));
+}
+
+pub fn update_powerup_text(
+    player_query: Query<(&PowerUps, &PlayerIndex)>,
+    mut text_query: Query<(&mut Text, &PlayerHud), With<PowerUpText>>,
+    config: Res<GameConfig>,
+) {
+    for (mut text, hud) in text_query.iter_mut() {
+        if let Some((powerups, _)) = player_query.iter().find(|(_, index)| index.0 == hud.0) {
+            text.sections[0].value = powerups
+                .active
+                .iter()
+                .map(|(kind, timer)| {
+                    let label = if *kind == PowerUpKind::ScoreMultiplier {
+                        format!("{}{}", kind.label(), config.powerups.score_multiplier)
+                    } else {
+                        kind.label().to_string()
+                    };
+                    format!("{label} {:.1}s", timer.remaining_secs())
+                })
+                .collect::<Vec<_>>()
+                .join("   ");
+        }
+    }
+}
+
+pub fn show_wave_banner(
+    mut commands: Commands,
+    mut wave_events: EventReader<WaveEvent>,
+    banner_query: Query<Entity, With<WaveBanner>>,
+) {
+    let Some(event) = wave_events.read().last() else {
+        return;
+    };
+    let message = match event {
+        WaveEvent::Started(wave) => format!("Wave {wave}"),
+        WaveEvent::Completed(wave) => format!("Wave {wave} cleared"),
+    };
+    for banner in banner_query.iter() {
+        commands.entity(banner).despawn_recursive();
+    }
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    top: Val::Percent(30.),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            WaveBanner {
+                timer: Timer::from_seconds(WAVE_BANNER_DURATION, TimerMode::Once),
+            },
+            StateScoped(InGame),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                message,
+                TextStyle {
+                    font_size: 60.0,
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Fades the banner out over its lifetime.
+pub fn update_wave_banner(
+    mut commands: Commands,
+    mut banner_query: Query<(Entity, &mut WaveBanner, &Children)>,
+    mut text_query: Query<&mut Text>,
+    time: Res<Time>,
+) {
+    for (entity, mut banner, children) in banner_query.iter_mut() {
+        banner.timer.tick(time.delta());
+        if banner.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let alpha = 1. - banner.timer.fraction().powi(3);
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                text.sections[0].style.color.set_alpha(alpha);
+            }
+        }
+    }
+}
+
+fn spawn_boss_health_bar(commands: &mut Commands, name: &str) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    top: Val::Px(60.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(4.),
+                    ..default()
+                },
+                ..default()
+            },
+            BossHealthBar,
+            StateScoped(InGame),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                name,
+                TextStyle {
+                    font_size: 30.0,
+                    ..default()
+                },
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(50.),
+                        height: Val::Px(16.),
+                        ..default()
+                    },
+                    background_color: Color::srgba(1., 1., 1., 0.2).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(100.),
+                                height: Val::Percent(100.),
+                                ..default()
+                            },
+                            background_color: Color::srgb(0.7, 0.4, 1.).into(),
+                            ..default()
+                        },
+                        BossHealthFill,
+                    ));
+                });
+        });
+}
+
+/// Shows a bar for the current boss, red once it is enraged, and removes it with the boss.
+pub fn update_boss_health_bar(
+    mut commands: Commands,
+    boss_query: Query<&Boss>,
+    bar_query: Query<Entity, With<BossHealthBar>>,
+    mut fill_query: Query<(&mut Style, &mut BackgroundColor), With<BossHealthFill>>,
+    definitions: Res<Assets<BossDefinition>>,
+) {
+    let Some((boss, definition)) = boss_query
+        .iter()
+        .find_map(|boss| definitions.get(&boss.definition).map(|def| (boss, def)))
+    else {
+        for bar in bar_query.iter() {
+            commands.entity(bar).despawn_recursive();
+        }
+        return;
+    };
+    if bar_query.is_empty() {
+        spawn_boss_health_bar(&mut commands, &definition.name);
+        return;
+    }
+    if let Ok((mut style, mut color)) = fill_query.get_single_mut() {
+        style.width = Val::Percent(100. * boss.health_fraction());
+        let enraged = definition
+            .phases
+            .get(boss.phase)
+            .is_some_and(|phase| phase.enraged);
+        *color = if enraged {
+            Color::srgb(1., 0.2, 0.2)
+        } else {
+            Color::srgb(0.7, 0.4, 1.)
+        }
+        .into();
+    }
+}
@@ -0,0 +1,658 @@
+use std::{
+    collections::BTreeMap,
+    io, mem,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    input::gamepad::{GamepadConnection, GamepadConnectionEvent},
+    prelude::*,
+    utils::HashSet,
+    window::PrimaryWindow,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{load_ron, save_ron, MovementMode, PlayerCount, MAX_PLAYERS};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Fire,
+    Reload,
+    SwitchWeapon,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 8] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::Fire,
+        Action::Reload,
+        Action::SwitchWeapon,
+        Action::Pause,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::Fire => "Fire",
+            Action::Reload => "Reload",
+            Action::SwitchWeapon => "Switch weapon",
+            Action::Pause => "Pause",
+        }
+    }
+
+    /// The second player sits at the numpad so both fit on one keyboard.
+    fn default_keys(self, player: usize) -> Vec<KeyCode> {
+        if player > 0 {
+            return match self {
+                Action::MoveLeft => vec![KeyCode::Numpad4],
+                Action::MoveRight => vec![KeyCode::Numpad6],
+                Action::MoveUp => vec![KeyCode::Numpad8],
+                Action::MoveDown => vec![KeyCode::Numpad5],
+                Action::Fire => vec![KeyCode::Numpad0],
+                Action::Reload => vec![KeyCode::NumpadDecimal],
+                Action::SwitchWeapon => vec![KeyCode::NumpadAdd],
+                Action::Pause => vec![KeyCode::NumpadEnter],
+            };
+        }
+        match self {
+            Action::MoveLeft => vec![KeyCode::KeyA, KeyCode::ArrowLeft],
+            Action::MoveRight => vec![KeyCode::KeyD, KeyCode::ArrowRight],
+            Action::MoveUp => vec![KeyCode::KeyW, KeyCode::ArrowUp],
+            Action::MoveDown => vec![KeyCode::KeyS, KeyCode::ArrowDown],
+            Action::Fire => vec![KeyCode::KeyJ, KeyCode::Space],
+            Action::Reload => vec![KeyCode::KeyR],
+            Action::SwitchWeapon => vec![KeyCode::KeyQ, KeyCode::Tab],
+            Action::Pause => vec![KeyCode::Escape, KeyCode::KeyP],
+        }
+    }
+
+    fn default_buttons(self) -> Vec<GamepadButtonType> {
+        match self {
+            Action::MoveLeft => vec![GamepadButtonType::DPadLeft],
+            Action::MoveRight => vec![GamepadButtonType::DPadRight],
+            Action::MoveUp => vec![GamepadButtonType::DPadUp],
+            Action::MoveDown => vec![GamepadButtonType::DPadDown],
+            Action::Fire => vec![
+                GamepadButtonType::RightTrigger2,
+                GamepadButtonType::RightTrigger,
+                GamepadButtonType::South,
+            ],
+            Action::Reload => vec![GamepadButtonType::West],
+            Action::SwitchWeapon => vec![GamepadButtonType::North, GamepadButtonType::LeftTrigger],
+            Action::Pause => vec![GamepadButtonType::Start],
+        }
+    }
+}
+
+fn default_dead_zone() -> f32 {
+    0.2
+}
+
+/// Keys and gamepad buttons bound to each action, any of them triggers it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct InputMap {
+    pub bindings: BTreeMap<Action, Vec<KeyCode>>,
+    #[serde(default)]
+    pub gamepad: BTreeMap<Action, Vec<GamepadButtonType>>,
+    /// Stick deflection below this is ignored.
+    #[serde(default = "default_dead_zone")]
+    pub dead_zone: f32,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self::for_player(0)
+    }
+}
+
+impl InputMap {
+    pub fn for_player(player: usize) -> Self {
+        Self {
+            bindings: Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_keys(player)))
+                .collect(),
+            gamepad: Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_buttons()))
+                .collect(),
+            dead_zone: default_dead_zone(),
+        }
+    }
+
+    /// Gives actions added since the map was saved their default bindings.
+    fn fill_missing(&mut self, player: usize) {
+        for action in Action::ALL {
+            self.bindings
+                .entry(action)
+                .or_insert_with(|| action.default_keys(player));
+            self.gamepad
+                .entry(action)
+                .or_insert_with(|| action.default_buttons());
+        }
+    }
+}
+
+/// Adds `input` to an action's bindings, taking it away from any other action. Without `append`
+/// the action's previous bindings are dropped.
+fn bind<T: PartialEq>(
+    bindings: &mut BTreeMap<Action, Vec<T>>,
+    action: Action,
+    input: T,
+    append: bool,
+) {
+    for bound in bindings.values_mut() {
+        bound.retain(|bound| *bound != input);
+    }
+    let bound = bindings.entry(action).or_default();
+    if !append {
+        bound.clear();
+    }
+    bound.push(input);
+}
+
+impl InputMap {
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn buttons(&self, action: Action) -> &[GamepadButtonType] {
+        self.gamepad.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn bind_key(&mut self, action: Action, key: KeyCode, append: bool) {
+        bind(&mut self.bindings, action, key, append);
+    }
+
+    pub fn bind_button(&mut self, action: Action, button: GamepadButtonType, append: bool) {
+        bind(&mut self.gamepad, action, button, append);
+    }
+
+    /// Left stick of the most deflected pad, rescaled so movement starts at the dead zone.
+    fn stick(&self, gamepads: &[Gamepad], axes: &Axis<GamepadAxis>) -> Vec2 {
+        let stick = gamepads
+            .iter()
+            .map(|gamepad| {
+                let axis = |axis_type| {
+                    axes.get(GamepadAxis::new(*gamepad, axis_type))
+                        .unwrap_or_default()
+                };
+                Vec2::new(
+                    axis(GamepadAxisType::LeftStickX),
+                    axis(GamepadAxisType::LeftStickY),
+                )
+            })
+            .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+            .unwrap_or_default();
+        let dead_zone = self.dead_zone.clamp(0., 0.99);
+        let length = stick.length().min(1.);
+        if length <= dead_zone {
+            return Vec2::ZERO;
+        }
+        stick.normalize() * (length - dead_zone) / (1. - dead_zone)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ControlMode {
+    /// Keyboard or gamepad steering.
+    #[default]
+    Buttons,
+    /// The ship follows the mouse or a finger sideways and fires on click or touch.
+    Pointer,
+}
+
+impl ControlMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            ControlMode::Buttons => "Keyboard / gamepad",
+            ControlMode::Pointer => "Mouse / touch",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            ControlMode::Buttons => ControlMode::Pointer,
+            ControlMode::Pointer => ControlMode::Buttons,
+        }
+    }
+}
+
+/// Player preferences kept between sessions.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub input: InputMap,
+    /// Controls of the second player in co-op.
+    pub second_input: InputMap,
+    pub control_mode: ControlMode,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            input: InputMap::for_player(0),
+            second_input: InputMap::for_player(1),
+            control_mode: ControlMode::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn input(&self, player: usize) -> &InputMap {
+        if player == 0 {
+            &self.input
+        } else {
+            &self.second_input
+        }
+    }
+
+    pub fn input_mut(&mut self, player: usize) -> &mut InputMap {
+        if player == 0 {
+            &mut self.input
+        } else {
+            &mut self.second_input
+        }
+    }
+
+    /// Reads the settings from `path`. A missing or unreadable file yields the defaults.
+    pub fn load(path: &Path) -> Self {
+        let Some(mut settings) = load_ron::<Settings>(path, "settings") else {
+            return Self::default();
+        };
+        settings.input.fill_missing(0);
+        settings.second_input.fill_missing(1);
+        settings
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        save_ron(path, self, true)
+    }
+}
+
+pub fn settings_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("spaceship-game").join("settings.ron"))
+}
+
+pub fn load_settings(mut commands: Commands) {
+    let settings = settings_path()
+        .map(|path| Settings::load(&path))
+        .unwrap_or_default();
+    commands.insert_resource(settings);
+}
+
+pub fn save_settings(settings: &Settings) {
+    if let Some(path) = settings_path() {
+        if let Err(err) = settings.save(&path) {
+            warn!("could not save settings to {}: {err}", path.display());
+        }
+    }
+}
+
+/// Actions one player holds and newly triggered this frame. Gameplay reads these instead of
+/// raw keys.
+#[derive(Default, Clone, Debug)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    /// Analog movement from a gamepad stick, zero inside the dead zone.
+    stick: Vec2,
+    /// World x the ship should move to in pointer mode.
+    pointer_x: Option<f32>,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// -1, 0 or 1 depending on which of the two opposing actions is held.
+    pub fn axis(&self, negative: Action, positive: Action) -> f32 {
+        f32::from(u8::from(self.pressed(positive))) - f32::from(u8::from(self.pressed(negative)))
+    }
+
+    /// Movement direction with components between -1 and 1 for a ship at `position`. The
+    /// stick wins over held actions, a pointer overrides the sideways part.
+    pub fn movement(&self, position: Vec2, max_step: f32) -> Vec2 {
+        let mut movement = if self.stick != Vec2::ZERO {
+            self.stick
+        } else {
+            Vec2::new(
+                self.axis(Action::MoveLeft, Action::MoveRight),
+                self.axis(Action::MoveDown, Action::MoveUp),
+            )
+        };
+        if let Some(x) = self.pointer_x {
+            // Scaled so the ship stops on the pointer instead of overshooting it.
+            movement.x = if max_step > 0. {
+                ((x - position.x) / max_step).clamp(-1., 1.)
+            } else {
+                0.
+            };
+        }
+        movement
+    }
+
+    /// Following a pointer always steers directly. Newtonian drift would carry the ship past
+    /// the pointer and back again.
+    pub fn movement_mode(&self, configured: MovementMode) -> MovementMode {
+        if self.pointer_x.is_some() {
+            MovementMode::Arcade
+        } else {
+            configured
+        }
+    }
+
+    pub fn pack(&self) -> PackedActions {
+        let bits = |set: &HashSet<Action>| {
+            Action::ALL
+                .iter()
+                .enumerate()
+                .filter(|(_, action)| set.contains(*action))
+                .fold(0, |bits, (i, _)| bits | 1 << i)
+        };
+        PackedActions {
+            pressed: bits(&self.pressed),
+            just_pressed: bits(&self.just_pressed),
+            stick: (self.stick != Vec2::ZERO).then_some((self.stick.x, self.stick.y)),
+            pointer_x: self.pointer_x,
+        }
+    }
+}
+
+/// An `ActionState` stored in a replay, with one bit per action.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+pub struct PackedActions {
+    pressed: u8,
+    just_pressed: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stick: Option<(f32, f32)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pointer_x: Option<f32>,
+}
+
+impl PackedActions {
+    pub fn unpack(self) -> ActionState {
+        let set = |bits: u8| {
+            Action::ALL
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| bits & 1 << i != 0)
+                .map(|(_, action)| action)
+                .collect()
+        };
+        ActionState {
+            pressed: set(self.pressed),
+            just_pressed: set(self.just_pressed),
+            stick: self.stick.map_or(Vec2::ZERO, |(x, y)| Vec2::new(x, y)),
+            pointer_x: self.pointer_x,
+        }
+    }
+}
+
+/// Action state of every player slot, the second one stays idle outside of co-op. As a
+/// resource it holds the input of the current fixed update.
+#[derive(Resource, Default, Debug)]
+pub struct Actions {
+    players: [ActionState; MAX_PLAYERS],
+}
+
+impl Actions {
+    pub fn player(&self, player: usize) -> &ActionState {
+        &self.players[player]
+    }
+
+    pub fn set_player(&mut self, player: usize, state: ActionState) {
+        self.players[player] = state;
+    }
+
+    pub fn any_just_pressed(&self, action: Action) -> bool {
+        self.players
+            .iter()
+            .any(|actions| actions.just_pressed(action))
+    }
+}
+
+/// Input read every frame, which menus use directly. Gameplay gets it through `Actions` once
+/// per fixed update.
+#[derive(Resource, Default, Debug)]
+pub struct LiveActions {
+    frame: Actions,
+    /// Presses not yet handed to a fixed update, so a tap between two updates isn't lost.
+    pending: [HashSet<Action>; MAX_PLAYERS],
+}
+
+impl Deref for LiveActions {
+    type Target = Actions;
+
+    fn deref(&self) -> &Actions {
+        &self.frame
+    }
+}
+
+/// Pads driving `player`. Alone the player takes every pad. In co-op a single pad goes to
+/// the second player, who has the smaller share of the keyboard, otherwise each gets their own.
+fn player_gamepads(gamepads: &[Gamepad], player: usize, players: usize) -> Vec<Gamepad> {
+    match (players, gamepads.len()) {
+        (1, _) => gamepads.to_vec(),
+        (_, 1) if player == 1 => gamepads.to_vec(),
+        (_, 1) => Vec::new(),
+        _ => gamepads.get(player).copied().into_iter().collect(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_action_state(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    win_query: Query<&Window, With<PrimaryWindow>>,
+    settings: Res<Settings>,
+    player_count: Res<PlayerCount>,
+    mut live: ResMut<LiveActions>,
+) {
+    let mut connected = gamepads.iter().collect::<Vec<_>>();
+    connected.sort_by_key(|gamepad| gamepad.id);
+    let players = player_count.0.clamp(1, MAX_PLAYERS);
+    let live = live.as_mut();
+    for (player, actions) in live.frame.players.iter_mut().enumerate() {
+        actions.pressed.clear();
+        actions.just_pressed.clear();
+        actions.stick = Vec2::ZERO;
+        actions.pointer_x = None;
+        if player >= players {
+            continue;
+        }
+        let input = settings.input(player);
+        let pads = player_gamepads(&connected, player, players);
+        for action in Action::ALL {
+            let keys_bound = input.keys(action).iter().copied();
+            let buttons_bound = pads
+                .iter()
+                .flat_map(|gamepad| {
+                    input
+                        .buttons(action)
+                        .iter()
+                        .map(move |button| GamepadButton::new(*gamepad, *button))
+                })
+                .collect::<Vec<_>>();
+            if keys.any_pressed(keys_bound.clone())
+                || buttons.any_pressed(buttons_bound.iter().copied())
+            {
+                actions.pressed.insert(action);
+            }
+            if keys.any_just_pressed(keys_bound) || buttons.any_just_pressed(buttons_bound) {
+                actions.just_pressed.insert(action);
+            }
+        }
+        actions.stick = input.stick(&pads, &axes);
+        // There is only one pointer, it belongs to the first player.
+        if player == 0 && settings.control_mode == ControlMode::Pointer {
+            let touch = touches.iter().next();
+            if touch.is_some() || mouse.pressed(MouseButton::Left) {
+                actions.pressed.insert(Action::Fire);
+            }
+            if touches.any_just_pressed() || mouse.just_pressed(MouseButton::Left) {
+                actions.just_pressed.insert(Action::Fire);
+            }
+            if let Ok(win) = win_query.get_single() {
+                // Screen coordinates start at the left edge, the camera is centred on 0.
+                actions.pointer_x = touch
+                    .map(|touch| touch.position())
+                    .or_else(|| win.cursor_position())
+                    .map(|position| position.x - win.width() / 2.);
+            }
+        }
+        live.pending[player].extend(actions.just_pressed.iter().copied());
+    }
+}
+
+/// Hands the input gathered since the last fixed update to gameplay.
+pub fn latch_actions(mut live: ResMut<LiveActions>, mut actions: ResMut<Actions>) {
+    for (player, state) in actions.players.iter_mut().enumerate() {
+        *state = ActionState {
+            just_pressed: mem::take(&mut live.pending[player]),
+            ..live.frame.players[player].clone()
+        };
+    }
+}
+
+/// Pads are read from `Gamepads` every frame, so plugging one in mid-run just works.
+pub fn log_gamepad_connections(mut events: EventReader<GamepadConnectionEvent>) {
+    for event in events.read() {
+        match &event.connection {
+            GamepadConnection::Connected(info) => {
+                info!("gamepad {} connected: {}", event.gamepad.id, info.name)
+            }
+            GamepadConnection::Disconnected => {
+                info!("gamepad {} disconnected", event.gamepad.id)
+            }
+        }
+    }
+}
+
+/// Short name for a gamepad button, as shown on the controls screen.
+pub fn button_name(button: GamepadButtonType) -> String {
+    format!("Pad {button:?}")
+}
+
+/// Short name for a key, as shown on the controls screen.
+pub fn key_name(key: KeyCode) -> String {
+    let name = format!("{key:?}");
+    name.strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+        .unwrap_or(&name)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn saved_settings_load_back() {
+        let mut settings = Settings::default();
+        settings.input.bind_key(Action::Fire, KeyCode::KeyK, false);
+        settings
+            .second_input
+            .bind_button(Action::Reload, GamepadButtonType::East, true);
+        settings.control_mode = ControlMode::Pointer;
+        crate::assert_persists(
+            "settings",
+            settings,
+            Settings::default(),
+            Settings::save,
+            Settings::load,
+        );
+    }
+
+    #[test]
+    fn actions_missing_from_the_file_get_defaults() {
+        let path = std::env::temp_dir().join(format!(
+            "spaceship-game-partial-settings-{}.ron",
+            std::process::id()
+        ));
+        fs::write(&path, "(input: (bindings: {Fire: [KeyK]}))").unwrap();
+        let settings = Settings::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(settings.input.keys(Action::Fire), [KeyCode::KeyK]);
+        assert_eq!(
+            settings.input.keys(Action::Pause),
+            Action::Pause.default_keys(0)
+        );
+        assert_eq!(
+            settings.input.buttons(Action::Fire),
+            Action::Fire.default_buttons()
+        );
+        assert_eq!(settings.second_input, InputMap::for_player(1));
+    }
+
+    #[test]
+    fn binding_a_key_takes_it_from_its_action() {
+        let mut input = InputMap::default();
+        input.bind_key(Action::Reload, KeyCode::Space, true);
+        assert_eq!(input.keys(Action::Fire), [KeyCode::KeyJ]);
+        assert_eq!(input.keys(Action::Reload), [KeyCode::KeyR, KeyCode::Space]);
+
+        input.bind_key(Action::Fire, KeyCode::KeyR, false);
+        assert_eq!(input.keys(Action::Fire), [KeyCode::KeyR]);
+        assert_eq!(input.keys(Action::Reload), [KeyCode::Space]);
+    }
+
+    fn stick(dead_zone: f32, x: f32, y: f32) -> Vec2 {
+        let input = InputMap {
+            dead_zone,
+            ..default()
+        };
+        let gamepad = Gamepad::new(0);
+        let mut axes = Axis::<GamepadAxis>::default();
+        axes.set(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX), x);
+        axes.set(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY), y);
+        input.stick(&[gamepad], &axes)
+    }
+
+    #[test]
+    fn stick_inside_dead_zone_is_ignored() {
+        assert_eq!(stick(0.2, 0.1, 0.1), Vec2::ZERO);
+        assert_eq!(stick(0.2, 0., -0.2), Vec2::ZERO);
+        let just_past = stick(0.2, 0.6, 0.);
+        assert!((just_past.x - 0.5).abs() < 1e-5, "{just_past}");
+    }
+
+    #[test]
+    fn full_deflection_has_length_one() {
+        for (x, y) in [(1., 0.), (0., -1.), (1., 1.)] {
+            let length = stick(0.2, x, y).length();
+            assert!((length - 1.).abs() < 1e-5, "({x}, {y}) gave {length}");
+        }
+    }
+
+    #[test]
+    fn out_of_range_dead_zone_is_clamped() {
+        let full = stick(5., 1., 0.);
+        assert!((full.length() - 1.).abs() < 1e-5, "{full}");
+        assert_eq!(stick(5., 0.9, 0.), Vec2::ZERO);
+        assert_eq!(stick(-1., 0.5, 0.), Vec2::new(0.5, 0.));
+    }
+}
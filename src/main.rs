@@ -1,17 +1,62 @@
-#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] 
-use asteroids::replace_asteroids;
-use asteroids::spawn_asteroids;
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+use asteroids::apply_asteroid_config;
+use asteroids::damage_asteroids;
+use asteroids::despawn_escaped_asteroids;
+use asteroids::despawn_hit_asteroids;
+use asteroids::split_asteroids;
 use asteroids::update_asteroids;
-use asteroids::Asteroid;
-use bevy::{prelude::*, window::PrimaryWindow};
+use asteroids::AsteroidDestroyed;
+use bevy::{
+    asset::{LoadState, LoadedFolder, RecursiveDependencyLoadState},
+    ecs::system::SystemParam,
+    input::InputSystem,
+    prelude::*,
+    window::PrimaryWindow,
+};
 
 mod asteroids;
 mod background;
+mod bosses;
+mod collision;
+mod config;
+mod damage;
+mod difficulty;
+mod effects;
+mod enemies;
+mod highscores;
 mod hud;
+mod input;
+mod menu;
 mod player;
+mod powerups;
+mod replay;
+mod rng;
+mod score;
+mod state;
+mod storage;
+mod waves;
+mod weapons;
 use crate::background::*;
+use crate::bosses::*;
+use crate::collision::*;
+use crate::config::*;
+use crate::damage::*;
+use crate::difficulty::*;
+use crate::effects::*;
+use crate::enemies::*;
+use crate::highscores::*;
 use crate::hud::*;
+use crate::input::*;
+use crate::menu::*;
 use crate::player::*;
+use crate::powerups::*;
+use crate::replay::*;
+use crate::rng::*;
+use crate::score::*;
+use crate::state::*;
+use crate::storage::*;
+use crate::waves::*;
+use crate::weapons::*;
 
 fn main() {
     App::new()
@@ -25,143 +70,460 @@ fn main() {
                                                                 //     ..default()
                                                                 // }),
         )
-        .add_systems(Startup, setup)
+        .init_asset::<GameConfig>()
+        .init_asset_loader::<GameConfigLoader>()
+        .init_asset::<BossDefinition>()
+        .init_asset_loader::<BossDefinitionLoader>()
+        .init_state::<GameState>()
+        .add_computed_state::<InGame>()
+        .enable_state_scoped_entities::<GameState>()
+        .enable_state_scoped_entities::<InGame>()
+        .add_plugins(gameplay)
+        .add_event::<RestartGame>()
+        .init_resource::<BackgroundRng>()
+        .add_systems(Startup, (setup, load_high_scores, load_settings))
+        .add_systems(PreUpdate, update_action_state.after(InputSystem))
+        .add_systems(OnEnter(GameState::MainMenu), (spawn_main_menu, stop_replay))
+        .add_systems(
+            OnEnter(GameState::GameOver),
+            (spawn_game_over_menu, save_replay),
+        )
+        .add_systems(OnEnter(GameState::Paused), (spawn_pause_menu, pause_time))
+        .add_systems(OnExit(GameState::Paused), resume_time)
         .add_systems(
             Update,
             (
                 execute_animations,
+                falling_stars.run_if(in_state(GameState::Playing)),
                 update_stars,
                 update_background,
-                falling_stars.run_if(alive),
-                player_inputs.run_if(alive),
-                fire_logic.run_if(alive),
+                update_menu_buttons,
+                log_gamepad_connections,
+                capture_rebinding.after(update_menu_buttons),
+                update_controls_screen.after(capture_rebinding),
+                restart_game.after(update_menu_buttons),
+                toggle_pause.run_if(in_state(InGame)),
+                update_name_entry.run_if(in_state(GameState::GameOver)),
+                reload_config.run_if(resource_exists::<GameConfig>),
+                finish_loading.run_if(in_state(GameState::Loading)),
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                sync_star_count,
+                apply_asteroid_config,
+                apply_player_config,
+                stop_recording_on_config_change,
+            )
+                .after(reload_config)
+                .run_if(resource_exists_and_changed::<GameConfig>),
+        )
+        .add_systems(
+            Update,
+            (
                 update_ammo_icon_pos,
                 update_ammo_text,
-                update_hearts_pos,
-                update_reloading_text.run_if(alive),
-                update_ammunition,
-                update_asteroids.run_if(alive),
-                replace_asteroids.run_if(alive),
-                detect_player_collision.run_if(alive),
-                loop_logic,
-                update_game_over_button,
-                detect_bullet_collision,
-            ),
+                update_ammo_icon_color,
+                update_weapon_text,
+                update_hearts,
+                update_reload_bar,
+                update_score_text.run_if(resource_changed::<Score>),
+                show_wave_banner,
+                update_wave_banner,
+                update_boss_health_bar,
+                update_powerup_text,
+            )
+                .run_if(in_state(InGame)),
         )
         .run();
 }
 
+/// Everything that plays out a run in fixed updates, driven only by the seed and the latched
+/// input. Menus, HUD and rendering are left to `main`.
+fn gameplay(app: &mut App) {
+    app.add_event::<ScoreEvent>()
+        .add_event::<Collision>()
+        .add_event::<BulletHitAsteroid>()
+        .add_event::<PlayerHitAsteroid>()
+        .add_event::<AsteroidDestroyed>()
+        .add_event::<BulletHitEnemy>()
+        .add_event::<BulletHitPlayer>()
+        .add_event::<PlayerHitEnemy>()
+        .add_event::<EnemyDestroyed>()
+        .add_event::<BossDefeated>()
+        .add_event::<PowerUpCollected>()
+        .add_event::<WaveEvent>()
+        .init_resource::<Score>()
+        .init_resource::<PlayerScores>()
+        .init_resource::<PlayerCount>()
+        .init_resource::<Actions>()
+        .init_resource::<LiveActions>()
+        .init_resource::<GameRng>()
+        .insert_resource(Time::<Fixed>::from_hz(TICK_RATE))
+        .add_systems(OnEnter(InGame), spawn_game)
+        .add_systems(
+            FixedPreUpdate,
+            (
+                latch_actions,
+                (
+                    play_back_actions.run_if(resource_exists::<ReplayPlayback>),
+                    record_actions.run_if(resource_exists::<ReplayRecorder>),
+                )
+                    .after(latch_actions)
+                    .run_if(in_state(GameState::Playing)),
+            ),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                tick_player_timers.before(player_inputs),
+                player_inputs,
+                fire_logic,
+                update_ammunition,
+                update_asteroids,
+                despawn_escaped_asteroids,
+                run_wave_director.run_if(wave_difficulty),
+                update_difficulty_curve.run_if(adaptive_difficulty),
+                spawn_endless_asteroids
+                    .after(update_difficulty_curve)
+                    .after(run_wave_director)
+                    .run_if(adaptive_difficulty),
+                detect_collisions
+                    .after(update_asteroids)
+                    .after(fire_logic)
+                    .after(player_inputs),
+                detect_player_collision.after(detect_collisions),
+                detect_bullet_collision.after(detect_collisions),
+                check_game_over,
+            )
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                // Everything rolling `GameRng` runs in a fixed order, the wave director and
+                // asteroid spawns above, then these, then powerup drops after the damage
+                // systems. Otherwise a replay could hand the same numbers to other rolls.
+                spawn_enemies.after(spawn_endless_asteroids),
+                move_enemies,
+                enemy_fire.after(move_enemies),
+                despawn_escaped_enemies.after(move_enemies),
+                detect_enemy_collision.after(detect_bullet_collision),
+                spawn_bosses.after(run_wave_director),
+                move_bosses,
+                boss_attacks.after(move_bosses).after(spawn_enemies),
+                update_powerups,
+                tick_powerups.before(tick_player_timers),
+                detect_powerup_collision.after(detect_collisions),
+                apply_powerups.after(detect_powerup_collision),
+                steer_homing.before(fire_logic),
+                update_invulnerability,
+                regenerate_shield,
+                revive_players.after(player_inputs),
+                tint_players.after(revive_players).after(regenerate_shield),
+                apply_knockback.after(player_inputs),
+            )
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                (
+                    despawn_hit_bullets,
+                    damage_player,
+                    damage_asteroids,
+                    damage_enemies,
+                    damage_bosses,
+                ),
+                (
+                    score_destroyed_asteroids,
+                    score_destroyed_enemies,
+                    score_defeated_bosses,
+                    drop_powerups,
+                    despawn_destroyed_enemies,
+                    play_explosion_sound,
+                    spawn_explosions,
+                    split_asteroids,
+                    despawn_hit_asteroids,
+                ),
+                apply_score_events,
+                update_explosions,
+            )
+                .chain()
+                .after(detect_player_collision)
+                .after(detect_bullet_collision)
+                .after(detect_enemy_collision)
+                .after(boss_attacks)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Resource)]
+pub struct GameAssets {
+    pub config: Handle<GameConfig>,
+    pub star: Handle<Image>,
+    pub star_layout: Handle<TextureAtlasLayout>,
+    pub space_ship: Handle<Image>,
+    pub ammo_icon: Handle<Image>,
+    pub heart: Handle<Image>,
+    pub asteroid: Handle<Image>,
+    pub bosses: Handle<LoadedFolder>,
+}
+
 fn setup(
     mut commands: Commands,
     assets: Res<AssetServer>,
     query: Query<&Window, With<PrimaryWindow>>,
 ) {
     commands.spawn(Camera2dBundle::default());
-    let star_texture: Handle<Image> = assets.load("../assets/Spritesheet/star.png");
     let layout = TextureAtlasLayout::from_grid(UVec2::splat(24), 3, 1, None, None);
-    let texture_atlas_layout = assets.add(layout);
-    let animation_config = AnimationConfig::new(0, 2, 5);
-    let space_ship_texture = assets.load("../assets/Spritesheet/spaceship1.png");
-    let ammo_icon_texture = assets.load("../assets/Spritesheet/ammo_icon.png");
-    let heart_texture = assets.load("../assets/Spritesheet/heart.png");
-    let asteroid_texture = assets.load("../assets/Spritesheet/asteroid.png");
+    commands.insert_resource(GameAssets {
+        config: assets.load("config.ron"),
+        star: assets.load("Spritesheet/star.png"),
+        star_layout: assets.add(layout),
+        space_ship: assets.load("Spritesheet/spaceship1.png"),
+        ammo_icon: assets.load("Spritesheet/ammo_icon.png"),
+        heart: assets.load("Spritesheet/heart.png"),
+        asteroid: assets.load("Spritesheet/asteroid.png"),
+        bosses: assets.load_folder("bosses"),
+    });
     if let Ok(win) = query.get_single() {
-        spawn_game_over_text(&mut commands);
-        spawn_score_text(&mut commands);
-        spawn_restart_button(&mut commands);
         spawn_background(&mut commands, win);
-        spawn_ammo_text(&mut commands);
-        spawn_ammo_icon(&mut commands, ammo_icon_texture, win);
-        spawn_stars(
-            &mut commands,
+    }
+}
+
+fn finish_loading(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    game_assets: Res<GameAssets>,
+    configs: Res<Assets<GameConfig>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let config = match assets.get_load_state(&game_assets.config) {
+        Some(LoadState::Loaded) => configs
+            .get(&game_assets.config)
+            .map(validated_config)
+            .unwrap_or_default(),
+        Some(LoadState::Failed(err)) => {
+            error!("could not load config.ron, using defaults: {err}");
+            GameConfig::default()
+        }
+        _ => return,
+    };
+    let loaded = [
+        &game_assets.star,
+        &game_assets.space_ship,
+        &game_assets.ammo_icon,
+        &game_assets.heart,
+        &game_assets.asteroid,
+    ]
+    .iter()
+    .all(|handle| assets.is_loaded_with_dependencies(*handle));
+    // A broken boss file shouldn't keep the game from starting, it is just skipped.
+    let bosses_settled = matches!(
+        assets.get_recursive_dependency_load_state(&game_assets.bosses),
+        Some(RecursiveDependencyLoadState::Loaded | RecursiveDependencyLoadState::Failed)
+    );
+    if loaded && bosses_settled {
+        commands.insert_resource(config);
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn spawn_run(
+    commands: &mut Commands,
+    game_assets: &GameAssets,
+    config: &GameConfig,
+    win: &Window,
+    player_count: usize,
+    playback: Option<&mut ReplayPlayback>,
+) {
+    let player_count = player_count.clamp(1, MAX_PLAYERS);
+    begin_run(commands, playback, player_count, win, config);
+    commands.insert_resource(Score::default());
+    commands.insert_resource(PlayerScores::default());
+    spawn_score_text(commands);
+    commands.insert_resource(WaveDirector::default());
+    commands.insert_resource(EnemySpawner::new(&config.enemies));
+    commands.insert_resource(DifficultyCurve::evaluate(
+        &config.difficulty,
+        config.asteroids.max_drift,
+        0.,
+        0,
+    ));
+    for index in 0..player_count {
+        let hud = PlayerHud(index);
+        spawn_ammo_text(commands, &config.weapons[0], hud);
+        spawn_weapon_text(commands, &config.weapons[0], hud);
+        spawn_ammo_icon(commands, game_assets.ammo_icon.clone(), win, hud);
+        spawn_reload_bar(commands, hud);
+        spawn_powerup_text(commands, hud);
+        spawn_spaceship(
+            commands,
+            &config.player,
+            &config.weapons,
+            game_assets.space_ship.clone(),
             win,
-            &star_texture,
-            &texture_atlas_layout,
-            &animation_config,
+            PlayerIndex(index),
+            player_count,
         );
-        spawn_asteroids(&mut commands, asteroid_texture, win);
-        spawn_reloading_text(&mut commands);
-        spawn_hearts(&mut commands, heart_texture, win);
-        spawn_spaceship(&mut commands, space_ship_texture, win);
     }
 }
 
-fn alive(hearts_query: Query<&Heart, With<Heart>>) -> bool {
-    let hearts = hearts_query.iter().count();
-    hearts > 0
+/// What it takes to start a run, shared by the first start and restarts.
+#[derive(SystemParam)]
+struct RunSpawner<'w, 's> {
+    game_assets: Res<'w, GameAssets>,
+    config: Res<'w, GameConfig>,
+    player_count: Res<'w, PlayerCount>,
+    playback: Option<ResMut<'w, ReplayPlayback>>,
+    win_query: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
 }
 
-fn loop_logic(
+impl RunSpawner<'_, '_> {
+    fn spawn(&mut self, commands: &mut Commands) {
+        if let Ok(win) = self.win_query.get_single() {
+            spawn_run(
+                commands,
+                &self.game_assets,
+                &self.config,
+                win,
+                self.player_count.0,
+                self.playback.as_deref_mut(),
+            );
+        }
+    }
+}
+
+fn spawn_game(mut commands: Commands, mut run: RunSpawner) {
+    run.spawn(&mut commands);
+}
+
+fn restart_game(
     mut commands: Commands,
-    mut visibility_queries: ParamSet<(
-        Query<&mut Visibility, With<Player>>,
-        Query<&mut Visibility, With<AmmoText>>,
-        Query<&mut Visibility, With<AmmoIcon>>,
-        Query<&mut Visibility, With<GameOverText>>,
-        Query<&mut Visibility, With<GameOverButton>>,
-        Query<(&mut Visibility, &mut Text), With<ScoreText>>,
-    )>,
-    bullets_query: Query<Entity, With<Bullet>>,
-    hearts_query: Query<&Heart, With<Heart>>,
-    mut asteroids_query: Query<&mut Transform, With<Asteroid>>,
-    mut player_query: Query<&mut Player, With<Player>>,
-    win_query: Query<&Window, With<PrimaryWindow>>,
+    mut events: EventReader<RestartGame>,
+    scoped_query: Query<Entity, With<StateScoped<InGame>>>,
+    mut run: RunSpawner,
 ) {
-    if let Ok(win) = win_query.get_single() {
-        if let Ok(mut player_visibility) = visibility_queries.p0().get_single_mut() {
-            if alive(hearts_query) {
-                *player_visibility = Visibility::Visible;
-                if let Ok(mut ammo_text_visibility) = visibility_queries.p1().get_single_mut() {
-                    *ammo_text_visibility = Visibility::Visible;
-                }
-                if let Ok(mut ammo_icon_visibility) = visibility_queries.p2().get_single_mut() {
-                    *ammo_icon_visibility = Visibility::Visible;
-                }
-                if let Ok(mut game_over_text_visibility) = visibility_queries.p3().get_single_mut()
-                {
-                    *game_over_text_visibility = Visibility::Hidden;
-                }
-                if let Ok(mut game_over_button_visibility) =
-                    visibility_queries.p4().get_single_mut()
-                {
-                    *game_over_button_visibility = Visibility::Hidden;
-                }
-                if let Ok((mut score_visibility, _)) = visibility_queries.p5().get_single_mut() {
-                    *score_visibility = Visibility::Visible;
-                }
-            } else {
-                if let Ok(mut player) = player_query.get_single_mut() {
-                    player.ammunition = 60;
-                }
-                for bullet in bullets_query.iter() {
-                    commands.entity(bullet).despawn();
-                }
-                *player_visibility = Visibility::Hidden;
-                if let Ok(mut ammo_text_visibility) = visibility_queries.p1().get_single_mut() {
-                    *ammo_text_visibility = Visibility::Hidden;
-                }
-                if let Ok(mut ammo_icon_visibility) = visibility_queries.p2().get_single_mut() {
-                    *ammo_icon_visibility = Visibility::Hidden;
-                }
-                if let Ok(mut game_over_text_visibility) = visibility_queries.p3().get_single_mut()
-                {
-                    *game_over_text_visibility = Visibility::Visible;
-                }
-                if let Ok(mut game_over_button_visibility) =
-                    visibility_queries.p4().get_single_mut()
-                {
-                    *game_over_button_visibility = Visibility::Visible;
-                }
-                if let Ok((mut score_visibility, mut text)) =
-                    visibility_queries.p5().get_single_mut()
-                {
-                    *score_visibility = Visibility::Hidden;
-                    text.sections[0].value = String::from("0");
-                }
-                for mut asteroid in asteroids_query.iter_mut() {
-                    asteroid.translation.y = win.resolution.height() / 2. + 25.;
-                }
-            }
+    if events.read().count() == 0 {
+        return;
+    }
+    for entity in scoped_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    run.spawn(&mut commands);
+}
+
+/// The run ends once every ship is down, a single downed co-op ship can still be revived.
+fn check_game_over(
+    player_query: Query<&Health, With<Player>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !player_query.is_empty() && player_query.iter().all(Health::is_dead) {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{asset::AssetPlugin, state::app::StatesPlugin, time::TimeUpdateStrategy};
+
+    use super::*;
+
+    /// Plays `ticks` fixed updates of a recorded run headless and returns where every ship,
+    /// bullet, rock and enemy ended up along with the score.
+    fn play(replay: &Replay, ticks: usize) -> (Vec<Vec3>, i64) {
+        let mut app = App::new();
+        let assets = AssetPlugin {
+            watch_for_changes_override: Some(false),
+            ..default()
+        };
+        app.add_plugins((MinimalPlugins, assets, StatesPlugin))
+            .init_asset::<Image>()
+            .init_asset::<AudioSource>()
+            .init_asset::<BossDefinition>()
+            .init_state::<GameState>()
+            .add_computed_state::<InGame>()
+            .add_plugins(gameplay)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+                1. / TICK_RATE,
+            )))
+            .insert_resource(GameAssets {
+                config: default(),
+                star: default(),
+                star_layout: default(),
+                space_ship: default(),
+                ammo_icon: default(),
+                heart: default(),
+                asteroid: default(),
+                bosses: default(),
+            })
+            .insert_resource(replay.config.clone())
+            .insert_resource(ReplayPlayback::new(replay.clone()));
+        app.world_mut().spawn((Window::default(), PrimaryWindow));
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+        for _ in 0..ticks {
+            app.update();
         }
+        let positions = app
+            .world_mut()
+            .query_filtered::<&Transform, Or<(
+                With<Player>,
+                With<Bullet>,
+                With<asteroids::Asteroid>,
+                With<Enemy>,
+            )>>()
+            .iter(app.world())
+            .map(|transform| transform.translation)
+            .collect();
+        (positions, app.world().resource::<Score>().0)
+    }
+
+    #[test]
+    fn same_seed_and_input_play_out_the_same() {
+        let window = Window::default();
+        let mut replay = Replay::new(
+            7,
+            1,
+            (window.width(), window.height()),
+            GameConfig::default(),
+        );
+        // Fire all the time while sweeping right and left.
+        let right = ron::from_str("(pressed: 18, just_pressed: 0)").unwrap();
+        let left = ron::from_str("(pressed: 17, just_pressed: 0)").unwrap();
+        for tick in 0..600 {
+            replay.push(vec![if tick / 90 % 2 == 0 { right } else { left }]);
+        }
+
+        let (positions, score) = play(&replay, 600);
+        assert!(positions.len() > 1, "nothing but the ship after 600 ticks");
+        assert_eq!(play(&replay, 600), (positions, score));
+    }
+
+    #[test]
+    fn game_over_waits_for_every_ship() {
+        let mut app = App::new();
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .add_systems(Update, check_game_over);
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+        let ships = [Health::from_hearts(3), Health::from_hearts(3)]
+            .map(|health| app.world_mut().spawn((Player::new(&[]), health)).id());
+        let down = |app: &mut App, ship: Entity| {
+            app.world_mut().get_mut::<Health>(ship).unwrap().current = 0;
+            app.update();
+            app.update();
+            *app.world().resource::<State<GameState>>().get()
+        };
+
+        assert_eq!(down(&mut app, ships[0]), GameState::Playing);
+        assert_eq!(down(&mut app, ships[1]), GameState::GameOver);
     }
 }
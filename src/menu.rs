@@ -0,0 +1,505 @@
+use bevy::{prelude::*, ui::FocusPolicy, window::PrimaryWindow};
+
+use crate::{
+    button_name, key_name, replay_path, save_settings, score_split, Action, GameConfig, GameState,
+    HighScores, InputMap, LiveActions, NameEntry, PlayerCount, PlayerScores, Replay,
+    ReplayPlayback, Score, Settings, MAX_PLAYERS,
+};
+
+#[derive(Event)]
+pub struct RestartGame;
+
+const BUTTON_COLOR: Color = Color::srgb(0.2, 0.2, 0.2);
+
+#[derive(Component)]
+pub struct GameOverText;
+
+#[derive(Component, Clone, Copy)]
+pub enum MenuButton {
+    Play,
+    PlayCoop,
+    /// Plays back the replay of the last finished run.
+    WatchReplay,
+    Resume,
+    Restart,
+    MainMenu,
+    Quit,
+    Controls,
+    CloseControls,
+    ResetControls,
+    ToggleControlMode,
+    /// Switches whose controls the screen edits.
+    SwitchPlayer,
+    /// Waits for a key to bind to `action`, added to its keys or replacing them.
+    Bind {
+        action: Action,
+        append: bool,
+    },
+}
+
+/// Rebinding screen drawn over the main or pause menu.
+#[derive(Component, Default)]
+pub struct ControlsScreen {
+    player: usize,
+    capturing: Option<(Action, bool)>,
+}
+
+#[derive(Component)]
+pub struct BindingText(Action);
+
+#[derive(Component)]
+pub struct ControlsHint;
+
+#[derive(Component)]
+pub struct ControlModeText;
+
+fn spawn_menu_root(commands: &mut Commands, state: GameState) -> Entity {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            },
+            StateScoped(state),
+        ))
+        .id()
+}
+
+fn spawn_menu_title(parent: &mut ChildBuilder, title: &str) {
+    parent.spawn(TextBundle::from_section(
+        title,
+        TextStyle {
+            font_size: 40.0,
+            ..default()
+        },
+    ));
+}
+
+fn spawn_menu_button(parent: &mut ChildBuilder, label: &str, action: MenuButton) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(150.0),
+                    padding: UiRect::all(Val::Px(5.0)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: BackgroundColor(BUTTON_COLOR),
+                ..default()
+            },
+            action,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle {
+                text: Text::from_section(
+                    label,
+                    TextStyle {
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                ..default()
+            });
+        });
+}
+
+fn spawn_high_score_table(parent: &mut ChildBuilder, high_scores: &HighScores) {
+    let style = TextStyle {
+        font_size: 20.0,
+        ..default()
+    };
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                margin: UiRect::top(Val::Px(20.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("High Scores", style.clone()));
+            if high_scores.entries.is_empty() {
+                parent.spawn(TextBundle::from_section("No scores yet", style.clone()));
+            }
+            for (rank, entry) in high_scores.entries.iter().enumerate() {
+                parent.spawn(TextBundle::from_section(
+                    format!("{:>2}. {:<12} {:>8}", rank + 1, entry.name, entry.score),
+                    style.clone(),
+                ));
+            }
+        });
+}
+
+pub fn spawn_main_menu(mut commands: Commands, high_scores: Res<HighScores>) {
+    let root = spawn_menu_root(&mut commands, GameState::MainMenu);
+    commands.entity(root).with_children(|parent| {
+        spawn_menu_title(parent, "Spaceship");
+        spawn_menu_button(parent, "Play", MenuButton::Play);
+        spawn_menu_button(parent, "Co-op", MenuButton::PlayCoop);
+        spawn_menu_button(parent, "Replay", MenuButton::WatchReplay);
+        spawn_menu_button(parent, "Controls", MenuButton::Controls);
+        spawn_menu_button(parent, "Quit", MenuButton::Quit);
+        spawn_high_score_table(parent, &high_scores);
+    });
+}
+
+pub fn spawn_game_over_menu(
+    mut commands: Commands,
+    score: Res<Score>,
+    player_scores: Res<PlayerScores>,
+    player_count: Res<PlayerCount>,
+    high_scores: Res<HighScores>,
+) {
+    let root = spawn_menu_root(&mut commands, GameState::GameOver);
+    commands.entity(root).with_children(|parent| {
+        parent.spawn((
+            TextBundle::from_section(
+                "Game Over",
+                TextStyle {
+                    font_size: 40.0,
+                    ..default()
+                },
+            ),
+            GameOverText,
+        ));
+        parent.spawn(TextBundle::from_section(
+            format!("Score: {}", score.0),
+            TextStyle {
+                font_size: 30.0,
+                ..default()
+            },
+        ));
+        let split = score_split(&player_scores, player_count.0);
+        if !split.is_empty() {
+            parent.spawn(TextBundle::from_section(
+                split,
+                TextStyle {
+                    font_size: 20.0,
+                    ..default()
+                },
+            ));
+        }
+        if high_scores.qualifies(score.0) {
+            parent.spawn((
+                TextBundle::from_section(
+                    "Name: _",
+                    TextStyle {
+                        font_size: 20.0,
+                        ..default()
+                    },
+                ),
+                NameEntry::default(),
+            ));
+        }
+        spawn_menu_button(parent, "Restart", MenuButton::Restart);
+        spawn_menu_button(parent, "Main Menu", MenuButton::MainMenu);
+    });
+}
+
+pub fn spawn_pause_menu(mut commands: Commands) {
+    let root = spawn_menu_root(&mut commands, GameState::Paused);
+    commands
+        .entity(root)
+        .insert(BackgroundColor(Color::srgba(0., 0., 0., 0.5)))
+        .with_children(|parent| {
+            spawn_menu_title(parent, "Paused");
+            spawn_menu_button(parent, "Resume", MenuButton::Resume);
+            spawn_menu_button(parent, "Restart", MenuButton::Restart);
+            spawn_menu_button(parent, "Controls", MenuButton::Controls);
+            spawn_menu_button(parent, "Quit", MenuButton::Quit);
+        });
+}
+
+fn spawn_controls_screen(commands: &mut Commands, state: GameState) {
+    let root = spawn_menu_root(commands, state);
+    let text_style = TextStyle {
+        font_size: 20.0,
+        ..default()
+    };
+    commands
+        .entity(root)
+        .insert((
+            BackgroundColor(Color::srgb(0.05, 0.05, 0.1)),
+            ZIndex::Global(1),
+            // Keeps clicks from reaching the menu underneath.
+            FocusPolicy::Block,
+            ControlsScreen::default(),
+        ))
+        .with_children(|parent| {
+            spawn_menu_title(parent, "Controls");
+            parent.spawn((
+                TextBundle::from_section("", text_style.clone()),
+                ControlModeText,
+            ));
+            spawn_menu_button(parent, "Switch player", MenuButton::SwitchPlayer);
+            spawn_menu_button(parent, "Switch mode", MenuButton::ToggleControlMode);
+            for action in Action::ALL {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(10.0),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(
+                            TextBundle::from_section(action.label(), text_style.clone())
+                                .with_style(Style {
+                                    width: Val::Px(160.0),
+                                    ..default()
+                                }),
+                        );
+                        parent.spawn((
+                            TextBundle::from_section("", text_style.clone()).with_style(Style {
+                                width: Val::Px(380.0),
+                                ..default()
+                            }),
+                            BindingText(action),
+                        ));
+                        spawn_menu_button(
+                            parent,
+                            "Set",
+                            MenuButton::Bind {
+                                action,
+                                append: false,
+                            },
+                        );
+                        spawn_menu_button(
+                            parent,
+                            "Add",
+                            MenuButton::Bind {
+                                action,
+                                append: true,
+                            },
+                        );
+                    });
+            }
+            parent.spawn((
+                TextBundle::from_section("", text_style.clone()),
+                ControlsHint,
+            ));
+            spawn_menu_button(parent, "Reset", MenuButton::ResetControls);
+            spawn_menu_button(parent, "Back", MenuButton::CloseControls);
+        });
+}
+
+/// Binds the next key or gamepad button pressed while the controls screen waits for one.
+/// Escape cancels.
+pub fn capture_rebinding(
+    input: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    mut screen_query: Query<&mut ControlsScreen>,
+    mut settings: ResMut<Settings>,
+) {
+    let Ok(mut screen) = screen_query.get_single_mut() else {
+        return;
+    };
+    let Some((action, append)) = screen.capturing else {
+        return;
+    };
+    let input_map = settings.input_mut(screen.player);
+    if let Some(key) = input.get_just_pressed().next().copied() {
+        screen.capturing = None;
+        if key != KeyCode::Escape {
+            input_map.bind_key(action, key, append);
+            save_settings(&settings);
+        }
+    } else if let Some(button) = buttons.get_just_pressed().next() {
+        screen.capturing = None;
+        input_map.bind_button(action, button.button_type, append);
+        save_settings(&settings);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_controls_screen(
+    screen_query: Query<&ControlsScreen>,
+    mut bindings_query: Query<
+        (&mut Text, &BindingText),
+        (Without<ControlsHint>, Without<ControlModeText>),
+    >,
+    mut hint_query: Query<&mut Text, (With<ControlsHint>, Without<ControlModeText>)>,
+    mut mode_query: Query<&mut Text, With<ControlModeText>>,
+    settings: Res<Settings>,
+) {
+    let Ok(screen) = screen_query.get_single() else {
+        return;
+    };
+    let input_map = settings.input(screen.player);
+    for (mut text, binding) in bindings_query.iter_mut() {
+        let names = input_map
+            .keys(binding.0)
+            .iter()
+            .map(|key| key_name(*key))
+            .chain(
+                input_map
+                    .buttons(binding.0)
+                    .iter()
+                    .map(|button| button_name(*button)),
+            )
+            .collect::<Vec<_>>();
+        text.sections[0].value = if names.is_empty() {
+            "unbound".to_string()
+        } else {
+            names.join(", ")
+        };
+    }
+    if let Ok(mut mode) = mode_query.get_single_mut() {
+        // Only the first player can steer with the pointer.
+        mode.sections[0].value = format!(
+            "Player {}   Mode: {}",
+            screen.player + 1,
+            settings.control_mode.label()
+        );
+    }
+    if let Ok(mut hint) = hint_query.get_single_mut() {
+        hint.sections[0].value = match screen.capturing {
+            Some((action, _)) => format!("Press a key for {}, Escape cancels", action.label()),
+            None => String::new(),
+        };
+    }
+}
+
+pub fn toggle_pause(
+    actions: Res<LiveActions>,
+    screen_query: Query<(), With<ControlsScreen>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // The controls screen has its own use for the keyboard.
+    if actions.any_just_pressed(Action::Pause) && screen_query.is_empty() {
+        match state.get() {
+            GameState::Playing => next_state.set(GameState::Paused),
+            GameState::Paused => next_state.set(GameState::Playing),
+            _ => {}
+        }
+    }
+}
+
+pub fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+pub fn resume_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn update_menu_buttons(
+    mut commands: Commands,
+    mut interaction_query: Query<
+        (&Interaction, &MenuButton, &mut BackgroundColor, &Children),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut text_query: Query<&mut Text>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut restart: EventWriter<RestartGame>,
+    mut exit: EventWriter<AppExit>,
+    mut screen_query: Query<(Entity, &mut ControlsScreen)>,
+    mut settings: ResMut<Settings>,
+    mut player_count: ResMut<PlayerCount>,
+    mut win_query: Query<&mut Window, With<PrimaryWindow>>,
+    config: Res<GameConfig>,
+) {
+    for (interaction, action, mut color, children) in &mut interaction_query {
+        let mut text = text_query.get_mut(children[0]).unwrap();
+        match *interaction {
+            Interaction::Pressed => match action {
+                MenuButton::Play => {
+                    player_count.0 = 1;
+                    next_state.set(GameState::Playing)
+                }
+                MenuButton::PlayCoop => {
+                    player_count.0 = MAX_PLAYERS;
+                    next_state.set(GameState::Playing)
+                }
+                MenuButton::WatchReplay => {
+                    let Some(replay) = replay_path().and_then(|path| Replay::load(&path)) else {
+                        info!("no replay to watch yet");
+                        continue;
+                    };
+                    if replay.config != *config {
+                        warn!("config.ron changed since the replay was recorded, not playing it");
+                        continue;
+                    }
+                    // Positions depend on the window size, so the replay needs the one it
+                    // was recorded at.
+                    if let Ok(mut win) = win_query.get_single_mut() {
+                        win.resolution.set(replay.window.0, replay.window.1);
+                    }
+                    player_count.0 = replay.players;
+                    commands.insert_resource(ReplayPlayback::new(replay));
+                    next_state.set(GameState::Playing)
+                }
+                MenuButton::Resume => next_state.set(GameState::Playing),
+                MenuButton::Restart => {
+                    // Leaving game over re-enters `InGame` and respawns everything on its
+                    // own, a paused run has to be torn down explicitly.
+                    if *state.get() == GameState::Paused {
+                        restart.send(RestartGame);
+                    }
+                    next_state.set(GameState::Playing)
+                }
+                MenuButton::MainMenu => next_state.set(GameState::MainMenu),
+                MenuButton::Quit => {
+                    exit.send(AppExit::Success);
+                }
+                MenuButton::Controls => {
+                    if screen_query.is_empty() {
+                        spawn_controls_screen(&mut commands, *state.get());
+                    }
+                }
+                MenuButton::CloseControls => {
+                    for (entity, _) in screen_query.iter() {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
+                MenuButton::ToggleControlMode => {
+                    settings.control_mode = settings.control_mode.next();
+                    save_settings(&settings);
+                }
+                MenuButton::SwitchPlayer => {
+                    for (_, mut screen) in screen_query.iter_mut() {
+                        screen.player = (screen.player + 1) % MAX_PLAYERS;
+                        screen.capturing = None;
+                    }
+                }
+                MenuButton::ResetControls => {
+                    for (_, screen) in screen_query.iter() {
+                        *settings.input_mut(screen.player) = InputMap::for_player(screen.player);
+                    }
+                    save_settings(&settings);
+                }
+                MenuButton::Bind { action, append } => {
+                    for (_, mut screen) in screen_query.iter_mut() {
+                        screen.capturing = Some((*action, *append));
+                    }
+                }
+            },
+            Interaction::Hovered => {
+                *color = BackgroundColor(Color::WHITE);
+                text.sections[0].style.color = Color::srgb(0., 0., 0.)
+            }
+            Interaction::None => {
+                *color = BackgroundColor(BUTTON_COLOR);
+                text.sections[0].style.color = Color::WHITE
+            }
+        }
+    }
+}
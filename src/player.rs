@@ -1,121 +1,395 @@
-use std::time::{Duration, Instant};
-
-use bevy::{audio::Volume, math::vec3, prelude::*, window::PrimaryWindow};
-use rand::Rng;
+use bevy::{
+    audio::Volume, ecs::system::EntityCommands, math::vec3, prelude::*, utils::HashSet,
+    window::PrimaryWindow,
+};
 
 use crate::{
-    asteroids::{Asteroid, ASTEROID_SIZE},
-    Heart, ScoreText,
+    asteroids::Asteroid, fire_weapon, Action, Actions, BulletHitAsteroid, BulletHitEnemy,
+    BulletHitPlayer, Collider, Collision, CollisionLayers, Downed, GameConfig, Health, InGame,
+    MovementMode, PlayerConfig, PlayerHitAsteroid, PowerUpKind, PowerUps, ShieldLayer, Weapon,
+    WeaponConfig,
 };
 
-const PLAYER_MOVEMENT_SPEED: f32 = 600.0;
-const SPACESHIP_SIZE: f32 = 80.0;
-const SHOT_SPEED: f32 = 400.0;
-const AMMUNITION_COUNT: usize = 60;
-const BULLET_SIZE: f32 = 20.;
+/// Ships flying in local co-op.
+pub const MAX_PLAYERS: usize = 2;
+
+/// Number of players in the current run, chosen on the main menu.
+#[derive(Resource)]
+pub struct PlayerCount(pub usize);
+
+impl Default for PlayerCount {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+/// Slot of a ship, picks its controls, colour and HUD corner.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlayerIndex(pub usize);
+
+impl PlayerIndex {
+    pub fn color(self) -> Color {
+        match self.0 {
+            0 => Color::WHITE,
+            _ => Color::srgb(1., 0.6, 0.3),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Faction {
+    Player,
+    Enemy,
+}
 
 #[derive(Component)]
-pub struct Bullet;
+pub struct Bullet {
+    pub faction: Faction,
+    pub velocity: Vec2,
+    pub damage: u32,
+    /// Piercing bullets keep going after a hit, but hit each target only once.
+    pub piercing: bool,
+    pub hits: HashSet<Entity>,
+    /// Ship that fired the bullet, scores go to it.
+    pub owner: Option<Entity>,
+}
+
+impl Bullet {
+    pub fn new(faction: Faction, velocity: Vec2) -> Self {
+        Self {
+            faction,
+            velocity,
+            damage: 1,
+            piercing: false,
+            hits: HashSet::new(),
+            owner: None,
+        }
+    }
+}
 
 #[derive(Component)]
 pub struct Player {
-    pub click_instant: Instant,
-    pub reload_instant: Option<Instant>,
-    pub ammunition: usize,
+    pub weapons: Vec<Weapon>,
+    pub active_weapon: usize,
+}
+
+impl Player {
+    pub fn new(weapons: &[WeaponConfig]) -> Self {
+        Self {
+            weapons: weapons.iter().map(Weapon::new).collect(),
+            active_weapon: 0,
+        }
+    }
+
+    pub fn weapon(&self) -> &Weapon {
+        &self.weapons[self.active_weapon]
+    }
+
+    pub fn weapon_mut(&mut self) -> &mut Weapon {
+        &mut self.weapons[self.active_weapon]
+    }
+
+    /// Switches to the next weapon, dropping a reload or burst that is still running.
+    pub fn cycle_weapon(&mut self) {
+        self.weapon_mut().holster();
+        self.active_weapon = (self.active_weapon + 1) % self.weapons.len();
+    }
+}
+
+/// Velocity of the ship, driven by the movement keys.
+#[derive(Component, Default)]
+pub struct PlayerMotion {
+    pub velocity: Vec2,
 }
 
-pub fn spawn_spaceship(commands: &mut Commands, texture: Handle<Image>, win: &Window) {
-    let spawn_point = -1.0 * (win.resolution.height() / 2.) + 80.;
+impl PlayerMotion {
+    /// Steers towards `input`, whose components are each between -1 and 1.
+    pub fn steer(&mut self, input: Vec2, mode: MovementMode, config: &PlayerConfig, dt: f32) {
+        match mode {
+            MovementMode::Arcade => self.velocity = input * config.movement_speed,
+            MovementMode::Newtonian => {
+                let drag = (-config.drag * dt).exp();
+                let velocity = self.velocity + input * config.acceleration * dt;
+                // Coasting axes drift to a halt, thrusting ones keep their speed.
+                self.velocity = Vec2::new(
+                    if input.x == 0. {
+                        velocity.x * drag
+                    } else {
+                        velocity.x
+                    },
+                    if input.y == 0. {
+                        velocity.y * drag
+                    } else {
+                        velocity.y
+                    },
+                )
+                .clamp_length_max(config.movement_speed);
+            }
+        }
+    }
+
+    /// Leans the ship into sideways movement.
+    pub fn bank(&self, config: &PlayerConfig) -> Quat {
+        let lean = (self.velocity.x / config.movement_speed).clamp(-1., 1.);
+        Quat::from_rotation_z(-lean * config.bank_angle.to_radians())
+    }
+}
+
+/// Area the ship's centre may move in, the bottom edge is the spawn line.
+pub fn play_area(win: &Window, config: &PlayerConfig) -> Rect {
+    let half_width = win.resolution.width() / 2. - config.size / 2.;
+    let height = win.resolution.height();
+    let bottom = spawn_height(win);
+    let top = (-height / 2. + height * config.vertical_band).max(bottom);
+    Rect::new(-half_width, bottom, half_width, top)
+}
+
+fn spawn_height(win: &Window) -> f32 {
+    -(win.resolution.height() / 2.) + 80.
+}
+
+/// Closest ship to `position` that is still flying.
+pub fn nearest_player<'a>(
+    players: impl IntoIterator<Item = &'a Transform>,
+    position: Vec2,
+) -> Option<Vec2> {
+    players
+        .into_iter()
+        .map(|transform| transform.translation.truncate())
+        .min_by(|a, b| {
+            a.distance_squared(position)
+                .total_cmp(&b.distance_squared(position))
+        })
+}
+
+pub fn player_layers() -> CollisionLayers {
+    CollisionLayers::new(
+        CollisionLayers::PLAYER,
+        CollisionLayers::ASTEROID
+            | CollisionLayers::ENEMY
+            | CollisionLayers::ENEMY_BULLET
+            | CollisionLayers::PICKUP,
+    )
+}
+
+/// Hull of the ship sprite, as fractions of its size.
+const HULL: [Vec2; 7] = [
+    Vec2::new(0., 0.45),
+    Vec2::new(0.45, 0.1),
+    Vec2::new(0.45, -0.2),
+    Vec2::new(0.15, -0.45),
+    Vec2::new(-0.15, -0.45),
+    Vec2::new(-0.45, -0.2),
+    Vec2::new(-0.45, 0.1),
+];
+
+pub fn spaceship_collider(size: f32) -> Collider {
+    Collider::ConvexPolygon {
+        points: HULL.iter().map(|p| *p * size).collect(),
+    }
+}
+
+pub fn bullet_collider(size: Vec2) -> Collider {
+    Collider::Aabb {
+        half_extents: Vec2::new(size.x / 4., size.y / 2.),
+    }
+}
+
+/// Spawns a projectile that only collides with the other faction.
+pub fn spawn_bullet<'a>(
+    commands: &'a mut Commands,
+    bullet: Bullet,
+    texture: Handle<Image>,
+    translation: Vec3,
+    size: Vec2,
+) -> EntityCommands<'a> {
+    let (layers, color) = match bullet.faction {
+        Faction::Player => (
+            CollisionLayers::new(
+                CollisionLayers::BULLET,
+                CollisionLayers::ASTEROID | CollisionLayers::ENEMY,
+            ),
+            Color::WHITE,
+        ),
+        Faction::Enemy => (
+            CollisionLayers::new(CollisionLayers::ENEMY_BULLET, CollisionLayers::PLAYER),
+            Color::srgb(1., 0.3, 0.3),
+        ),
+    };
     commands.spawn((
         SpriteBundle {
             transform: Transform {
-                translation: vec3(0., spawn_point, 0.0),
+                translation,
+                rotation: Quat::from_rotation_arc_2d(
+                    Vec2::Y,
+                    bullet.velocity.normalize_or(Vec2::Y),
+                ),
                 ..default()
             },
             sprite: Sprite {
-                custom_size: Some(Vec2::splat(SPACESHIP_SIZE)),
+                color,
+                custom_size: Some(size),
                 ..default()
             },
             texture,
             ..default()
         },
-        Player {
-            click_instant: Instant::now(),
-            reload_instant: None,
-            ammunition: AMMUNITION_COUNT,
+        bullet,
+        bullet_collider(size),
+        layers,
+        StateScoped(InGame),
+    ))
+}
+
+pub fn spawn_spaceship(
+    commands: &mut Commands,
+    config: &PlayerConfig,
+    weapons: &[WeaponConfig],
+    texture: Handle<Image>,
+    win: &Window,
+    index: PlayerIndex,
+    count: usize,
+) {
+    let spawn_point = spawn_height(win);
+    // In co-op the ships start a third of the screen apart.
+    let x = if count > 1 {
+        (index.0 as f32 - 0.5) * win.resolution.width() / 3.
+    } else {
+        0.
+    };
+    commands.spawn((
+        SpriteBundle {
+            transform: Transform {
+                translation: vec3(x, spawn_point, 0.0),
+                ..default()
+            },
+            sprite: Sprite {
+                color: index.color(),
+                custom_size: Some(Vec2::splat(config.size)),
+                ..default()
+            },
+            texture,
+            ..default()
         },
+        Player::new(weapons),
+        index,
+        PlayerMotion::default(),
+        PowerUps::default(),
+        Health::from_hearts(config.hearts),
+        ShieldLayer::new(config),
+        spaceship_collider(config.size),
+        player_layers(),
+        StateScoped(InGame),
     ));
 }
 
+pub fn apply_player_config(
+    config: Res<GameConfig>,
+    mut player_query: Query<(&mut Sprite, &mut Collider, &mut Player)>,
+) {
+    for (mut sprite, mut collider, mut player) in player_query.iter_mut() {
+        sprite.custom_size = Some(Vec2::splat(config.player.size));
+        *collider = spaceship_collider(config.player.size);
+        if player.weapons.len() == config.weapons.len() {
+            for (weapon, stats) in player.weapons.iter_mut().zip(&config.weapons) {
+                weapon.set_stats(stats);
+            }
+        } else {
+            *player = Player::new(&config.weapons);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
 pub fn player_inputs(
     mut commands: Commands,
-    mut player_query: Query<(&mut Transform, &mut Player), With<Player>>,
+    mut player_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Player,
+            &PowerUps,
+            &mut PlayerMotion,
+            &PlayerIndex,
+        ),
+        Without<Downed>,
+    >,
     win_query: Query<&Window, With<PrimaryWindow>>,
     assets: Res<AssetServer>,
+    config: Res<GameConfig>,
     time: Res<Time>,
-    input: Res<ButtonInput<KeyCode>>,
+    actions: Res<Actions>,
 ) {
     if let Ok(win) = win_query.get_single() {
-        if let Ok(mut player) = player_query.get_single_mut() {
-            let texture = assets.load("../assets/Spritesheet/fire.png");
-            let laser = assets.load("../assets/Laser.ogg");
-            let dt = time.delta_seconds();
-            let half_width = win.resolution.width() / 2.0;
-
-            if input.pressed(KeyCode::KeyD) {
-                player.0.translation.x += dt * PLAYER_MOVEMENT_SPEED;
-            } else if input.pressed(KeyCode::KeyA) {
-                player.0.translation.x -= dt * PLAYER_MOVEMENT_SPEED;
+        let texture = assets.load("Spritesheet/fire.png");
+        let laser = assets.load("Laser.ogg");
+        let dt = time.delta_seconds();
+        let area = play_area(win, &config.player);
+        for mut player in player_query.iter_mut() {
+            let actions = actions.player(player.5 .0);
+            let movement = actions.movement(
+                player.1.translation.truncate(),
+                config.player.movement_speed * dt,
+            );
+            let mode = actions.movement_mode(config.player.movement_mode);
+            player.4.steer(movement, mode, &config.player, dt);
+            let moved = player.1.translation.truncate() + player.4.velocity * dt;
+            let clamped = moved.clamp(area.min, area.max);
+            // Running into the edge of the play area stops the ship along that axis.
+            if clamped.x != moved.x {
+                player.4.velocity.x = 0.;
+            }
+            if clamped.y != moved.y {
+                player.4.velocity.y = 0.;
+            }
+            player.1.translation = clamped.extend(player.1.translation.z);
+            player.1.rotation = player.4.bank(&config.player);
+            if actions.just_pressed(Action::SwitchWeapon) {
+                player.2.cycle_weapon();
             }
-            if input.pressed(KeyCode::KeyJ)
-                && player.1.click_instant.elapsed() > Duration::from_millis(100)
-                && player.1.ammunition > 0
-            {
+            let fired = actions.pressed(Action::Fire) && player.2.weapon_mut().try_fire();
+            let shots = usize::from(fired) + usize::from(player.2.weapon_mut().take_burst_shot());
+            let extra_spread = player
+                .3
+                .is_active(PowerUpKind::SpreadShot)
+                .then_some(config.powerups.spread_angle);
+            for _ in 0..shots {
                 commands.spawn(AudioBundle {
-                    source: laser,
+                    source: laser.clone(),
                     settings: PlaybackSettings {
                         volume: Volume::new(0.05),
                         ..default()
                     },
                 });
-                commands.spawn((
-                    SpriteBundle {
-                        transform: Transform {
-                            translation: vec3(player.0.translation.x, player.0.translation.y, -1.0),
-                            ..default()
-                        },
-                        sprite: Sprite {
-                            custom_size: Some(Vec2::splat(BULLET_SIZE)),
-                            ..default()
-                        },
-                        texture,
-                        ..default()
-                    },
-                    Bullet,
-                ));
-                player.1.click_instant = Instant::now();
-                player.1.ammunition -= 1;
+                fire_weapon(
+                    &mut commands,
+                    player.0,
+                    &player.2.weapon().stats,
+                    texture.clone(),
+                    vec3(player.1.translation.x, player.1.translation.y, -1.0),
+                    config.bullet.size,
+                    extra_spread,
+                );
             }
-            player.0.translation.x = player.0.translation.x.clamp(
-                -half_width + SPACESHIP_SIZE / 2.,
-                half_width - SPACESHIP_SIZE / 2.,
-            );
         }
     }
 }
 
 pub fn fire_logic(
-    mut fire_query: Query<(&mut Transform, Entity), With<Bullet>>,
+    mut fire_query: Query<(&mut Transform, &Bullet, Entity)>,
     mut commands: Commands,
     win_query: Query<&Window, With<PrimaryWindow>>,
     time: Res<Time>,
 ) {
     if let Ok(win) = win_query.get_single() {
-        for (mut transform, shot) in fire_query.iter_mut() {
-            if transform.translation.y > win.resolution.height() / 2. - 10. {
+        let half_width = win.resolution.width() / 2. + 10.;
+        let half_height = win.resolution.height() / 2. + 10.;
+        for (mut transform, bullet, shot) in fire_query.iter_mut() {
+            let position = transform.translation;
+            if position.x.abs() > half_width || position.y.abs() > half_height {
                 commands.entity(shot).despawn()
             } else {
-                transform.translation.y += time.delta_seconds() * SHOT_SPEED
+                transform.translation += (bullet.velocity * time.delta_seconds()).extend(0.)
             }
         }
     }
@@ -123,13 +397,15 @@ pub fn fire_logic(
 
 pub fn update_ammunition(
     mut commands: Commands,
-    mut player_query: Query<&mut Player, With<Player>>,
+    mut player_query: Query<(&mut Player, &PlayerIndex), Without<Downed>>,
     assets: Res<AssetServer>,
+    actions: Res<Actions>,
 ) {
-    if let Ok(mut player) = player_query.get_single_mut() {
-        if player.ammunition == 0 && player.reload_instant.is_none() {
-            player.reload_instant = Some(Instant::now());
-            let reload = assets.load("../assets/reload.ogg");
+    for (mut player, index) in player_query.iter_mut() {
+        let wants_reload =
+            player.weapon().ammunition == 0 || actions.player(index.0).just_pressed(Action::Reload);
+        if wants_reload && player.weapon_mut().start_reload() {
+            let reload = assets.load("reload.ogg");
             commands.spawn(AudioBundle {
                 source: reload,
                 settings: PlaybackSettings {
@@ -138,88 +414,422 @@ pub fn update_ammunition(
                 },
             });
         }
-        if let Some(inst) = player.reload_instant {
-            if inst.elapsed() > Duration::from_secs(2) {
-                player.reload_instant = None;
-                player.ammunition = 60;
-            }
-        }
+    }
+}
+
+pub fn tick_player_timers(
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    mut player_query: Query<(&mut Player, Option<&PowerUps>)>,
+) {
+    for (mut player, powerups) in player_query.iter_mut() {
+        let cooldown = if powerups.is_some_and(|p| p.is_active(PowerUpKind::RapidFire)) {
+            time.delta().mul_f32(config.powerups.rapid_fire_factor)
+        } else {
+            time.delta()
+        };
+        player.weapon_mut().tick(time.delta(), cooldown);
     }
 }
 
 pub fn detect_player_collision(
-    mut commands: Commands,
-    mut query: ParamSet<(
-        Query<&Transform, With<Player>>,
-        Query<&mut Transform, With<Asteroid>>,
-    )>,
-    mut hearts_query: Query<Entity, With<Heart>>,
-    win_query: Query<&Window, With<PrimaryWindow>>,
+    mut collisions: EventReader<Collision>,
+    player_query: Query<(), With<Player>>,
+    asteroids_query: Query<(), With<Asteroid>>,
+    mut hits: EventWriter<PlayerHitAsteroid>,
 ) {
-    if let Ok(win) = win_query.get_single() {
-        if let Ok(player) = query.p0().get_single() {
-            let player_coords = player.translation.truncate();
-            let player_radius = SPACESHIP_SIZE / 2.;
-
-            for mut asteroid in query.p1().iter_mut() {
-                let asteroid_coords = asteroid.translation.truncate();
-                let asteroid_radius = asteroid.scale.x / 2.;
-                let distance = player_coords.distance(asteroid_coords);
-
-                if distance < player_radius + asteroid_radius {
-                    asteroid.translation.y = win.height() / 2.;
-
-                    let mut hearts = hearts_query.iter_mut().collect::<Vec<_>>();
-                    if let Some(heart) = hearts.last_mut() {
-                        commands.entity(*heart).despawn();
-                    }
-                }
-            }
+    let mut handled = HashSet::new();
+    for collision in collisions.read() {
+        let Some((player, asteroid)) = collision.ordered_by(|e| player_query.contains(e)) else {
+            continue;
+        };
+        if asteroids_query.contains(asteroid) && handled.insert(asteroid) {
+            hits.send(PlayerHitAsteroid { player, asteroid });
         }
     }
 }
 
+/// Each bullet and each asteroid takes part in at most one hit per frame, so a
+/// bullet overlapping two rocks is only spent once. Piercing bullets may hit several
+/// asteroids, but never the same one twice.
 pub fn detect_bullet_collision(
+    mut collisions: EventReader<Collision>,
+    mut bullets_query: Query<&mut Bullet>,
+    asteroids_query: Query<(), With<Asteroid>>,
+    mut hits: EventWriter<BulletHitAsteroid>,
+) {
+    let mut handled = HashSet::new();
+    for collision in collisions.read() {
+        let Some((bullet, asteroid)) = collision.ordered_by(|e| bullets_query.contains(e)) else {
+            continue;
+        };
+        let Ok(mut state) = bullets_query.get_mut(bullet) else {
+            continue;
+        };
+        if !asteroids_query.contains(asteroid)
+            || handled.contains(&bullet)
+            || state.hits.contains(&asteroid)
+            || !handled.insert(asteroid)
+        {
+            continue;
+        }
+        state.hits.insert(asteroid);
+        if !state.piercing {
+            handled.insert(bullet);
+        }
+        hits.send(BulletHitAsteroid {
+            bullet,
+            asteroid,
+            damage: state.damage,
+            owner: state.owner,
+        });
+    }
+}
+
+pub fn despawn_hit_bullets(
     mut commands: Commands,
-    bullets_query: Query<(&Transform, Entity), (With<Bullet>, Without<Asteroid>)>,
-    mut asteroids_query: Query<&mut Transform, (With<Asteroid>, Without<Bullet>)>,
-    mut score_query: Query<&mut Text, With<ScoreText>>,
-    win_query: Query<&Window, With<PrimaryWindow>>,
-    assets: Res<AssetServer>,
+    mut asteroid_hits: EventReader<BulletHitAsteroid>,
+    mut enemy_hits: EventReader<BulletHitEnemy>,
+    mut player_hits: EventReader<BulletHitPlayer>,
+    bullets_query: Query<&Bullet>,
 ) {
-    if let Ok(win) = win_query.get_single() {
-        let explosion = assets.load("../assets/Explosion.ogg");
-        for bullet in bullets_query.iter() {
-            let bullet_coords = bullet.0.translation.truncate();
-            let bullet_radius = BULLET_SIZE / 2.;
-
-            for mut asteroid in asteroids_query.iter_mut() {
-                let asteroid_coords = asteroid.translation.truncate();
-                let asteroid_radius = ASTEROID_SIZE / 2.;
-
-                let distance = bullet_coords.distance(asteroid_coords);
-
-                if distance < bullet_radius + asteroid_radius {
-                    commands.spawn(AudioBundle {
-                        source: explosion.clone(),
-                        settings: PlaybackSettings {
-                            volume: Volume::new(0.1),
-                            ..default()
-                        },
-                    });
-                    commands.entity(bullet.1).despawn();
-                    asteroid.translation.y = win.height() / 2. + 50.;
-                    let mut rng = rand::thread_rng();
-                    let x = rng.gen_range(
-                        -win.resolution.width() / 2.0 + 50. ..win.resolution.width() / 2. - 50.,
-                    );
-                    asteroid.translation.x = x;
-                    if let Ok(mut score) = score_query.get_single_mut() {
-                        let value = score.sections[0].value.parse::<i64>().unwrap();
-                        score.sections[0].value = format!("{}", value + 100);
-                    }
-                }
+    let bullets: HashSet<Entity> = asteroid_hits
+        .read()
+        .map(|hit| hit.bullet)
+        .chain(enemy_hits.read().map(|hit| hit.bullet))
+        .chain(player_hits.read().map(|hit| hit.bullet))
+        .collect();
+    for bullet in bullets {
+        if bullets_query
+            .get(bullet)
+            .is_ok_and(|bullet| !bullet.piercing)
+        {
+            commands.entity(bullet).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::world::CommandQueue;
+
+    use super::*;
+    use crate::{
+        asteroids::AsteroidSize, detect_enemy_collision, Enemy, EnemyKind, PlayerHitEnemy,
+        WeaponPattern,
+    };
+
+    fn setup_app() -> (App, Entity) {
+        let mut app = App::new();
+        app.init_resource::<Time>();
+        app.init_resource::<GameConfig>();
+        app.add_systems(Update, tick_player_timers);
+        let player = app
+            .world_mut()
+            .spawn(Player::new(&GameConfig::default().weapons))
+            .id();
+        (app, player)
+    }
+
+    fn advance(app: &mut App, millis: u64) {
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_millis(millis));
+        app.update();
+    }
+
+    fn weapon(app: &mut App, entity: Entity) -> Mut<'_, Weapon> {
+        app.world_mut()
+            .get_mut::<Player>(entity)
+            .unwrap()
+            .map_unchanged(|player| player.weapon_mut())
+    }
+
+    fn magazine() -> usize {
+        GameConfig::default().weapons[0].magazine
+    }
+
+    fn collision_app() -> App {
+        let mut app = App::new();
+        app.add_event::<Collision>()
+            .add_event::<BulletHitAsteroid>()
+            .add_event::<BulletHitEnemy>()
+            .add_event::<BulletHitPlayer>()
+            .add_event::<PlayerHitEnemy>()
+            .add_systems(
+                Update,
+                (detect_bullet_collision, detect_enemy_collision).chain(),
+            );
+        app
+    }
+
+    fn spawn_asteroid(app: &mut App) -> Entity {
+        app.world_mut()
+            .spawn(Asteroid {
+                size: AsteroidSize::Small,
+                hit_points: 1,
+            })
+            .id()
+    }
+
+    fn sent<E: Event>(app: &App) -> usize {
+        app.world()
+            .resource::<Events<E>>()
+            .iter_current_update_events()
+            .count()
+    }
+
+    #[test]
+    fn bullet_over_two_asteroids_hits_once() {
+        let mut app = collision_app();
+        let bullet = app
+            .world_mut()
+            .spawn(Bullet::new(Faction::Player, Vec2::Y))
+            .id();
+        let first = spawn_asteroid(&mut app);
+        let second = spawn_asteroid(&mut app);
+        app.world_mut().send_event(Collision(bullet, first));
+        app.world_mut().send_event(Collision(second, bullet));
+        app.update();
+
+        assert_eq!(sent::<BulletHitAsteroid>(&app), 1);
+    }
+
+    #[test]
+    fn piercing_bullet_hits_each_asteroid_once() {
+        let mut app = collision_app();
+        let bullet = app
+            .world_mut()
+            .spawn(Bullet {
+                piercing: true,
+                ..Bullet::new(Faction::Player, Vec2::Y)
+            })
+            .id();
+        let asteroids = [spawn_asteroid(&mut app), spawn_asteroid(&mut app)];
+        for _ in 0..2 {
+            for asteroid in asteroids {
+                app.world_mut().send_event(Collision(bullet, asteroid));
             }
         }
+        app.update();
+        assert_eq!(sent::<BulletHitAsteroid>(&app), 2);
+
+        // Still overlapping both on the next tick.
+        for asteroid in asteroids {
+            app.world_mut().send_event(Collision(bullet, asteroid));
+        }
+        app.update();
+        assert_eq!(sent::<BulletHitAsteroid>(&app), 0);
+    }
+
+    #[test]
+    fn bullet_over_asteroid_and_enemy_hits_once() {
+        let mut app = collision_app();
+        let bullet = app
+            .world_mut()
+            .spawn(Bullet::new(Faction::Player, Vec2::Y))
+            .id();
+        let asteroid = spawn_asteroid(&mut app);
+        let enemy = app
+            .world_mut()
+            .spawn(Enemy {
+                kind: EnemyKind::Strafer,
+                health: 1,
+                fire_timer: Timer::default(),
+            })
+            .id();
+        app.world_mut().send_event(Collision(enemy, bullet));
+        app.world_mut().send_event(Collision(bullet, asteroid));
+        app.update();
+
+        assert_eq!(
+            sent::<BulletHitAsteroid>(&app) + sent::<BulletHitEnemy>(&app),
+            1
+        );
+    }
+
+    #[test]
+    fn fire_rate_is_limited_by_cooldown() {
+        let (mut app, entity) = setup_app();
+        assert!(weapon(&mut app, entity).try_fire());
+        assert!(!weapon(&mut app, entity).try_fire());
+
+        advance(&mut app, 60);
+        assert!(!weapon(&mut app, entity).try_fire());
+        advance(&mut app, 40);
+        assert!(weapon(&mut app, entity).try_fire());
+        assert_eq!(weapon(&mut app, entity).ammunition, magazine() - 2);
+    }
+
+    #[test]
+    fn empty_magazine_cannot_fire() {
+        let (mut app, entity) = setup_app();
+        weapon(&mut app, entity).ammunition = 0;
+        advance(&mut app, 500);
+        assert!(!weapon(&mut app, entity).try_fire());
+    }
+
+    #[test]
+    fn reload_completes_after_reload_time() {
+        let (mut app, entity) = setup_app();
+        weapon(&mut app, entity).ammunition = 0;
+        assert!(weapon(&mut app, entity).start_reload());
+        assert!(!weapon(&mut app, entity).start_reload());
+
+        advance(&mut app, 1500);
+        assert_eq!(weapon(&mut app, entity).ammunition, 0);
+
+        advance(&mut app, 500);
+        let weapon = weapon(&mut app, entity);
+        assert_eq!(weapon.ammunition, magazine());
+        assert!(weapon.reload_timer.is_none());
+    }
+
+    #[test]
+    fn full_magazine_cannot_reload() {
+        let (mut app, entity) = setup_app();
+        assert!(!weapon(&mut app, entity).start_reload());
+    }
+
+    #[test]
+    fn partial_magazine_reloads_to_full() {
+        let (mut app, entity) = setup_app();
+        weapon(&mut app, entity).ammunition = 5;
+        assert!(weapon(&mut app, entity).start_reload());
+        advance(&mut app, 100);
+        assert!(!weapon(&mut app, entity).try_fire());
+
+        advance(&mut app, 1900);
+        assert_eq!(weapon(&mut app, entity).ammunition, magazine());
+    }
+
+    #[test]
+    fn reload_draws_from_limited_reserve() {
+        let (mut app, entity) = setup_app();
+        {
+            let mut weapon = weapon(&mut app, entity);
+            weapon.ammunition = 0;
+            weapon.reserve = Some(10);
+            assert!(weapon.start_reload());
+        }
+        advance(&mut app, 2000);
+        let mut weapon = weapon(&mut app, entity);
+        assert_eq!(weapon.ammunition, 10);
+        assert_eq!(weapon.reserve, Some(0));
+
+        weapon.ammunition = 0;
+        assert!(!weapon.start_reload());
+    }
+
+    #[test]
+    fn switching_weapons_cancels_reload() {
+        let (mut app, entity) = setup_app();
+        weapon(&mut app, entity).ammunition = 0;
+        assert!(weapon(&mut app, entity).start_reload());
+
+        let mut player = app.world_mut().get_mut::<Player>(entity).unwrap();
+        player.cycle_weapon();
+        assert!(player.weapons[0].reload_timer.is_none());
+        assert_eq!(player.weapons[0].ammunition, 0);
+    }
+
+    #[test]
+    fn switching_weapons_drops_a_burst() {
+        let (mut app, entity) = setup_app();
+        app.world_mut()
+            .get_mut::<Player>(entity)
+            .unwrap()
+            .active_weapon = 2;
+        assert!(weapon(&mut app, entity).try_fire());
+
+        let mut player = app.world_mut().get_mut::<Player>(entity).unwrap();
+        for _ in 0..player.weapons.len() {
+            player.cycle_weapon();
+        }
+        assert_eq!(player.active_weapon, 2);
+        advance(&mut app, 100);
+        assert!(!weapon(&mut app, entity).take_burst_shot());
+    }
+
+    fn fire(stats: &WeaponConfig) -> World {
+        let mut world = World::new();
+        let mut queue = CommandQueue::default();
+        let owner = world.spawn_empty().id();
+        let mut commands = Commands::new(&mut queue, &world);
+        fire_weapon(
+            &mut commands,
+            owner,
+            stats,
+            Handle::default(),
+            Vec3::ZERO,
+            20.,
+            None,
+        );
+        queue.apply(&mut world);
+        world
+    }
+
+    #[test]
+    fn spread_fans_bullets_evenly_over_its_angle() {
+        let stats = &GameConfig::default().weapons[1];
+        assert_eq!(
+            stats.pattern,
+            WeaponPattern::Spread {
+                count: 5,
+                angle: 40.
+            }
+        );
+        let mut world = fire(stats);
+        let mut angles: Vec<f32> = world
+            .query::<&Bullet>()
+            .iter(&world)
+            .map(|bullet| {
+                assert!((bullet.velocity.length() - stats.speed).abs() < 0.01);
+                Vec2::Y.angle_between(bullet.velocity).to_degrees()
+            })
+            .collect();
+        angles.sort_by(f32::total_cmp);
+        for (angle, expected) in angles.iter().zip([-20., -10., 0., 10., 20.]) {
+            assert!((angle - expected).abs() < 0.01, "angles {angles:?}");
+        }
+        assert_eq!(angles.len(), 5);
+    }
+
+    #[test]
+    fn laser_is_one_long_piercing_bolt() {
+        let stats = &GameConfig::default().weapons[3];
+        let mut world = fire(stats);
+        let bullets: Vec<_> = world.query::<(&Bullet, &Sprite)>().iter(&world).collect();
+        assert_eq!(bullets.len(), 1);
+        let (bullet, sprite) = bullets[0];
+        assert!(bullet.piercing);
+        assert_eq!(bullet.damage, stats.damage);
+        assert_eq!(sprite.custom_size, Some(Vec2::new(20., 300.)));
+    }
+
+    #[test]
+    fn newtonian_ship_settles_on_the_pointer() {
+        let config = PlayerConfig {
+            movement_mode: MovementMode::Newtonian,
+            ..default()
+        };
+        let actions = ron::from_str::<crate::PackedActions>(
+            "(pressed: 0, just_pressed: 0, pointer_x: Some(200.0))",
+        )
+        .unwrap()
+        .unpack();
+        let mut motion = PlayerMotion::default();
+        let mut position = Vec2::ZERO;
+        let dt = 1. / 60.;
+        for _ in 0..120 {
+            let movement = actions.movement(position, config.movement_speed * dt);
+            let mode = actions.movement_mode(config.movement_mode);
+            motion.steer(movement, mode, &config, dt);
+            position += motion.velocity * dt;
+        }
+        assert!((position.x - 200.).abs() < 0.01, "ship at {}", position.x);
+        assert_eq!(motion.velocity.x, 0.);
     }
 }
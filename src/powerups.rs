@@ -0,0 +1,256 @@
+use bevy::{prelude::*, utils::HashSet, window::PrimaryWindow};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AsteroidDestroyed, Collider, Collision, CollisionLayers, GameAssets, GameConfig, GameRng,
+    Health, InGame, Player, PowerUpConfig,
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PowerUpKind {
+    ExtraHeart,
+    InstantReload,
+    Shield,
+    RapidFire,
+    SpreadShot,
+    ScoreMultiplier,
+}
+
+impl PowerUpKind {
+    /// Whether the effect lasts for a while instead of applying once.
+    pub fn is_timed(self) -> bool {
+        matches!(
+            self,
+            PowerUpKind::Shield
+                | PowerUpKind::RapidFire
+                | PowerUpKind::SpreadShot
+                | PowerUpKind::ScoreMultiplier
+        )
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PowerUpKind::ExtraHeart => "Heart",
+            PowerUpKind::InstantReload => "Reload",
+            PowerUpKind::Shield => "Shield",
+            PowerUpKind::RapidFire => "Rapid",
+            PowerUpKind::SpreadShot => "Spread",
+            PowerUpKind::ScoreMultiplier => "Score x",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            PowerUpKind::ExtraHeart | PowerUpKind::InstantReload => Color::WHITE,
+            PowerUpKind::Shield => Color::srgb(0.3, 0.7, 1.),
+            PowerUpKind::RapidFire => Color::srgb(1., 0.9, 0.2),
+            PowerUpKind::SpreadShot => Color::srgb(0.4, 1., 0.4),
+            PowerUpKind::ScoreMultiplier => Color::srgb(1., 0.4, 1.),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+}
+
+/// Timed effects currently running on a player.
+#[derive(Component, Default)]
+pub struct PowerUps {
+    pub active: Vec<(PowerUpKind, Timer)>,
+}
+
+impl PowerUps {
+    pub fn is_active(&self, kind: PowerUpKind) -> bool {
+        self.active.iter().any(|(active, _)| *active == kind)
+    }
+
+    /// Starts an effect, or restarts it if it is already running.
+    pub fn activate(&mut self, kind: PowerUpKind, duration: f32) {
+        self.active.retain(|(active, _)| *active != kind);
+        self.active
+            .push((kind, Timer::from_seconds(duration, TimerMode::Once)));
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PowerUpCollected {
+    pub player: Entity,
+    pub pickup: Entity,
+    pub kind: PowerUpKind,
+}
+
+fn roll_drop(config: &PowerUpConfig, rng: &mut impl Rng) -> Option<PowerUpKind> {
+    if !rng.gen_bool(config.drop_chance.clamp(0., 1.) as f64) {
+        return None;
+    }
+    let total: u32 = config.drops.iter().map(|drop| drop.weight).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut roll = rng.gen_range(0..total);
+    for drop in &config.drops {
+        if roll < drop.weight {
+            return Some(drop.kind);
+        }
+        roll -= drop.weight;
+    }
+    None
+}
+
+pub fn drop_powerups(
+    mut commands: Commands,
+    mut destroyed: EventReader<AsteroidDestroyed>,
+    game_assets: Res<GameAssets>,
+    config: Res<GameConfig>,
+    mut rng: ResMut<GameRng>,
+) {
+    let config = &config.powerups;
+    for event in destroyed.read() {
+        let Some(kind) = roll_drop(config, rng.as_mut()) else {
+            continue;
+        };
+        let texture = match kind {
+            PowerUpKind::ExtraHeart => game_assets.heart.clone(),
+            PowerUpKind::InstantReload => game_assets.ammo_icon.clone(),
+            _ => Handle::default(),
+        };
+        commands.spawn((
+            SpriteBundle {
+                transform: Transform::from_translation(event.translation.with_z(0.5)),
+                sprite: Sprite {
+                    color: kind.color(),
+                    custom_size: Some(Vec2::splat(config.size)),
+                    ..default()
+                },
+                texture,
+                ..default()
+            },
+            PowerUp { kind },
+            Collider::Circle {
+                radius: config.size / 2.,
+            },
+            CollisionLayers::new(CollisionLayers::PICKUP, CollisionLayers::PLAYER),
+            StateScoped(InGame),
+        ));
+    }
+}
+
+pub fn update_powerups(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform), With<PowerUp>>,
+    win_query: Query<&Window, With<PrimaryWindow>>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+) {
+    if let Ok(win) = win_query.get_single() {
+        for (entity, mut transform) in query.iter_mut() {
+            transform.translation.y -= config.powerups.fall_speed * time.delta_seconds();
+            if transform.translation.y < -win.height() / 2. - config.powerups.size {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+pub fn detect_powerup_collision(
+    mut collisions: EventReader<Collision>,
+    pickups_query: Query<&PowerUp>,
+    player_query: Query<(), With<Player>>,
+    mut collected: EventWriter<PowerUpCollected>,
+) {
+    let mut handled = HashSet::new();
+    for collision in collisions.read() {
+        let Some((pickup, player)) = collision.ordered_by(|e| pickups_query.contains(e)) else {
+            continue;
+        };
+        if !player_query.contains(player) || !handled.insert(pickup) {
+            continue;
+        }
+        if let Ok(powerup) = pickups_query.get(pickup) {
+            collected.send(PowerUpCollected {
+                player,
+                pickup,
+                kind: powerup.kind,
+            });
+        }
+    }
+}
+
+pub fn apply_powerups(
+    mut commands: Commands,
+    mut collected: EventReader<PowerUpCollected>,
+    mut player_query: Query<(&mut Player, &mut Health, &mut PowerUps)>,
+    config: Res<GameConfig>,
+) {
+    for event in collected.read() {
+        commands.entity(event.pickup).despawn();
+        let Ok((mut player, mut health, mut powerups)) = player_query.get_mut(event.player) else {
+            continue;
+        };
+        info!("picked up {}", event.kind.label());
+        match event.kind {
+            PowerUpKind::ExtraHeart => {
+                if health.max < config.powerups.max_hearts as u32 * 2 {
+                    health.max += 2;
+                }
+                health.heal(2);
+            }
+            PowerUpKind::InstantReload => {
+                let weapon = player.weapon_mut();
+                weapon.reload_timer = None;
+                weapon.ammunition = weapon.stats.magazine;
+            }
+            kind => powerups.activate(kind, config.powerups.duration(kind)),
+        }
+    }
+}
+
+pub fn tick_powerups(time: Res<Time>, mut query: Query<&mut PowerUps>) {
+    for mut powerups in query.iter_mut() {
+        powerups
+            .active
+            .retain_mut(|(_, timer)| !timer.tick(time.delta()).finished());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extra_heart_grows_health_up_to_max_hearts() {
+        let mut app = App::new();
+        app.init_resource::<GameConfig>()
+            .add_event::<PowerUpCollected>()
+            .add_systems(Update, apply_powerups);
+        let mut health = Health::from_hearts(4);
+        health.damage(5);
+        let player = app
+            .world_mut()
+            .spawn((
+                Player::new(&GameConfig::default().weapons),
+                health,
+                PowerUps::default(),
+            ))
+            .id();
+        let collect = |app: &mut App| {
+            let pickup = app.world_mut().spawn_empty().id();
+            app.world_mut().send_event(PowerUpCollected {
+                player,
+                pickup,
+                kind: PowerUpKind::ExtraHeart,
+            });
+            app.update();
+            let health = app.world().get::<Health>(player).unwrap();
+            (health.current, health.max)
+        };
+
+        assert_eq!(collect(&mut app), (5, 10));
+        assert_eq!(collect(&mut app), (7, 10));
+        assert_eq!(collect(&mut app), (9, 10));
+        assert_eq!(collect(&mut app), (10, 10));
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{load_ron, save_ron, Actions, GameConfig, GameRng, PackedActions, MAX_PLAYERS};
+
+/// Fixed updates per second. Gameplay only advances in these steps, so a replay goes
+/// through exactly the same updates as the run it was recorded from.
+pub const TICK_RATE: f64 = 60.;
+
+/// Seed and per-update input of a run, enough to play it again under the same config.
+/// Identical updates in a row are stored once with a repeat count.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub players: usize,
+    /// Window size of the run, the play area and spawn positions depend on it.
+    pub window: (f32, f32),
+    /// Config the run was played with, a replay only plays out the same under it.
+    pub config: GameConfig,
+    ticks: Vec<(u32, Vec<PackedActions>)>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, players: usize, window: (f32, f32), config: GameConfig) -> Self {
+        Self {
+            seed,
+            players,
+            window,
+            config,
+            ticks: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, actions: Vec<PackedActions>) {
+        match self.ticks.last_mut() {
+            Some((repeat, last)) if *last == actions => *repeat += 1,
+            _ => self.ticks.push((1, actions)),
+        }
+    }
+
+    /// Number of fixed updates recorded.
+    pub fn tick_count(&self) -> usize {
+        self.ticks.iter().map(|(repeat, _)| *repeat as usize).sum()
+    }
+
+    /// Reads a replay from `path`, `None` if there is none or it can't be read.
+    pub fn load(path: &Path) -> Option<Self> {
+        let replay = load_ron::<Replay>(path, "replay")?;
+        if !(1..=MAX_PLAYERS).contains(&replay.players) {
+            warn!("ignoring replay for {} players", replay.players);
+            return None;
+        }
+        Some(replay)
+    }
+
+    /// Replays are written without pretty printing, they are long and not meant for editing.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        save_ron(path, self, false)
+    }
+}
+
+pub fn replay_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("spaceship-game").join("replay.ron"))
+}
+
+/// Replay of the run being played.
+#[derive(Resource)]
+pub struct ReplayRecorder(pub Replay);
+
+/// Replay being watched, it feeds the recorded input to gameplay instead of the live one.
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    /// Index into the stored ticks and how often that tick has been repeated so far.
+    cursor: (usize, u32),
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            cursor: (0, 0),
+        }
+    }
+
+    pub fn rewind(&mut self) {
+        self.cursor = (0, 0);
+    }
+
+    fn next_tick(&mut self) -> Option<&[PackedActions]> {
+        let (index, repeated) = &mut self.cursor;
+        let (repeat, actions) = self.replay.ticks.get(*index)?;
+        *repeated += 1;
+        if *repeated >= *repeat {
+            *index += 1;
+            *repeated = 0;
+        }
+        Some(actions)
+    }
+}
+
+/// Seeds a new run and starts recording it, or starts the watched replay over.
+pub fn begin_run(
+    commands: &mut Commands,
+    playback: Option<&mut ReplayPlayback>,
+    players: usize,
+    win: &Window,
+    config: &GameConfig,
+) {
+    let seed = match playback {
+        Some(playback) => {
+            playback.rewind();
+            playback.replay.seed
+        }
+        None => {
+            let seed = rand::random();
+            commands.insert_resource(ReplayRecorder(Replay::new(
+                seed,
+                players,
+                (win.width(), win.height()),
+                config.clone(),
+            )));
+            seed
+        }
+    };
+    info!("starting run with seed {seed}");
+    commands.insert_resource(GameRng::new(seed));
+}
+
+/// Swaps the live input for the recorded one while watching a replay. Once the recording
+/// runs out the ships just stop.
+pub fn play_back_actions(mut playback: ResMut<ReplayPlayback>, mut actions: ResMut<Actions>) {
+    let recorded = playback.next_tick().map(<[PackedActions]>::to_vec);
+    for player in 0..MAX_PLAYERS {
+        let state = recorded
+            .as_ref()
+            .and_then(|recorded| recorded.get(player))
+            .map(|packed| packed.unpack())
+            .unwrap_or_default();
+        actions.set_player(player, state);
+    }
+}
+
+pub fn record_actions(mut recorder: ResMut<ReplayRecorder>, actions: Res<Actions>) {
+    let players = recorder.0.players;
+    recorder
+        .0
+        .push((0..players).map(|i| actions.player(i).pack()).collect());
+}
+
+pub fn save_replay(recorder: Option<Res<ReplayRecorder>>) {
+    let (Some(recorder), Some(path)) = (recorder, replay_path()) else {
+        return;
+    };
+    match recorder.0.save(&path) {
+        Ok(()) => info!(
+            "saved replay of {} updates to {}",
+            recorder.0.tick_count(),
+            path.display()
+        ),
+        Err(err) => warn!("could not save replay to {}: {err}", path.display()),
+    }
+}
+
+/// A config change mid-run means the recording could no longer be played back, so the
+/// run goes unrecorded from there on.
+pub fn stop_recording_on_config_change(
+    mut commands: Commands,
+    config: Res<GameConfig>,
+    recorder: Option<Res<ReplayRecorder>>,
+) {
+    if let Some(recorder) = recorder {
+        if recorder.0.config != *config {
+            info!("config.ron changed mid-run, this run won't be saved as a replay");
+            commands.remove_resource::<ReplayRecorder>();
+        }
+    }
+}
+
+/// Leaving for the main menu ends both recording and watching.
+pub fn stop_replay(mut commands: Commands) {
+    commands.remove_resource::<ReplayRecorder>();
+    commands.remove_resource::<ReplayPlayback>();
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, RngCore};
+
+    use super::*;
+
+    fn tick(fire: bool) -> Vec<PackedActions> {
+        let pressed = if fire { 1 << 4 } else { 0 };
+        vec![ron::from_str(&format!("(pressed: {pressed}, just_pressed: 0)")).unwrap()]
+    }
+
+    #[test]
+    fn packed_actions_survive_unpacking() {
+        let packed: PackedActions = ron::from_str(
+            "(pressed: 21, just_pressed: 16, stick: Some((0.5, -1.0)), pointer_x: Some(12.5))",
+        )
+        .unwrap();
+        let state = packed.unpack();
+        assert!(state.pressed(crate::Action::Fire));
+        assert!(state.just_pressed(crate::Action::Fire));
+        assert!(!state.pressed(crate::Action::MoveRight));
+        assert_eq!(state.pack(), packed);
+    }
+
+    #[test]
+    fn identical_ticks_are_stored_once() {
+        let mut replay = Replay::new(1, 1, (800., 600.), GameConfig::default());
+        for fire in [false, false, true, true, true, false] {
+            replay.push(tick(fire));
+        }
+        assert_eq!(replay.ticks.len(), 3);
+        assert_eq!(replay.tick_count(), 6);
+    }
+
+    #[test]
+    fn playback_repeats_ticks_in_order() {
+        let mut replay = Replay::new(1, 1, (800., 600.), GameConfig::default());
+        for fire in [false, true, true] {
+            replay.push(tick(fire));
+        }
+        let mut playback = ReplayPlayback::new(replay);
+        let played: Vec<_> =
+            std::iter::from_fn(|| playback.next_tick().map(<[_]>::to_vec)).collect();
+        assert_eq!(played, vec![tick(false), tick(true), tick(true)]);
+        assert_eq!(playback.next_tick(), None);
+        playback.rewind();
+        assert_eq!(playback.next_tick(), Some(tick(false).as_slice()));
+    }
+
+    #[test]
+    fn saved_replay_loads_back() {
+        let mut replay = Replay::new(42, 2, (1280., 720.), GameConfig::default());
+        replay.push(vec![PackedActions::default(); 2]);
+        crate::assert_persists(
+            "replay",
+            Some(replay),
+            None,
+            |replay, path| replay.as_ref().unwrap().save(path),
+            Replay::load,
+        );
+    }
+
+    #[test]
+    fn config_change_stops_recording() {
+        let mut app = App::new();
+        app.init_resource::<GameConfig>()
+            .insert_resource(ReplayRecorder(Replay::new(
+                1,
+                1,
+                (800., 600.),
+                GameConfig::default(),
+            )))
+            .add_systems(Update, stop_recording_on_config_change);
+        app.update();
+        assert!(app.world().contains_resource::<ReplayRecorder>());
+
+        app.world_mut().resource_mut::<GameConfig>().stars.count += 1;
+        app.update();
+        assert!(!app.world().contains_resource::<ReplayRecorder>());
+    }
+
+    #[test]
+    fn same_seed_rolls_the_same_numbers() {
+        let rolls = |seed| {
+            let mut rng = GameRng::new(seed);
+            (0..8).map(|_| rng.gen_range(0..1000)).collect::<Vec<_>>()
+        };
+        assert_eq!(rolls(7), rolls(7));
+        assert_ne!(rolls(7), rolls(8));
+    }
+
+    #[test]
+    fn seeded_rolls_match_older_replays() {
+        // Replays only store the seed, if these change old replays play out differently.
+        let mut rng = GameRng::new(42);
+        let rolls: Vec<u32> = (0..4).map(|_| rng.next_u32()).collect();
+        assert_eq!(rolls, vec![962419617, 2928721845, 628724104, 4081401798]);
+    }
+}
@@ -0,0 +1,50 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Random numbers for gameplay. Every run starts from a fresh seed, so a replay of the run
+/// rolls the same numbers again. `StdRng` doesn't promise the same stream across rand
+/// versions, so this names its generator to keep old replay files playable.
+#[derive(Resource)]
+pub struct GameRng(ChaCha8Rng);
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self(ChaCha8Rng::seed_from_u64(seed))
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(rand::random())
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+/// Random numbers for the starfield, kept apart from `GameRng` so window resizes and star
+/// count changes don't shift what gameplay rolls.
+#[derive(Resource)]
+pub struct BackgroundRng(pub StdRng);
+
+impl Default for BackgroundRng {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
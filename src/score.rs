@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+
+use crate::{
+    AsteroidDestroyed, BossDefeated, EnemyDestroyed, GameConfig, PlayerIndex, PowerUpKind,
+    PowerUps, MAX_PLAYERS,
+};
+
+#[derive(Resource, Default)]
+pub struct Score(pub i64);
+
+/// Share of the score each player earned, the total stays in `Score`.
+#[derive(Resource, Default)]
+pub struct PlayerScores(pub [i64; MAX_PLAYERS]);
+
+#[derive(Debug, Clone, Copy)]
+pub enum ScoreReason {
+    AsteroidDestroyed,
+    EnemyDestroyed,
+    BossDefeated,
+}
+
+#[derive(Event)]
+pub struct ScoreEvent {
+    pub amount: i64,
+    pub reason: ScoreReason,
+    /// Player credited with the points.
+    pub player: Option<Entity>,
+}
+
+/// Points earned by a player use their own multiplier, unattributed ones count as boosted
+/// while any player has it.
+pub fn apply_score_events(
+    mut events: EventReader<ScoreEvent>,
+    mut score: ResMut<Score>,
+    mut player_scores: ResMut<PlayerScores>,
+    powerups_query: Query<(&PowerUps, Option<&PlayerIndex>)>,
+    config: Res<GameConfig>,
+) {
+    let boosted = |powerups: &PowerUps| powerups.is_active(PowerUpKind::ScoreMultiplier);
+    for event in events.read() {
+        let player = event
+            .player
+            .and_then(|player| powerups_query.get(player).ok());
+        let multiplied = match player {
+            Some((powerups, _)) => boosted(powerups),
+            None => powerups_query.iter().any(|(powerups, _)| boosted(powerups)),
+        };
+        let amount = if multiplied {
+            event.amount * config.powerups.score_multiplier
+        } else {
+            event.amount
+        };
+        debug!("score {:+} ({:?})", amount, event.reason);
+        score.0 += amount;
+        if let Some((_, Some(index))) = player {
+            player_scores.0[index.0] += amount;
+        }
+    }
+}
+
+pub fn score_destroyed_asteroids(
+    mut destroyed: EventReader<AsteroidDestroyed>,
+    mut score_events: EventWriter<ScoreEvent>,
+    config: Res<GameConfig>,
+) {
+    for event in destroyed.read() {
+        score_events.send(ScoreEvent {
+            amount: config.asteroids.get(event.size).score,
+            reason: ScoreReason::AsteroidDestroyed,
+            player: event.destroyed_by,
+        });
+    }
+}
+
+pub fn score_destroyed_enemies(
+    mut destroyed: EventReader<EnemyDestroyed>,
+    mut score_events: EventWriter<ScoreEvent>,
+    config: Res<GameConfig>,
+) {
+    for event in destroyed.read() {
+        score_events.send(ScoreEvent {
+            amount: config.enemies.get(event.kind).score,
+            reason: ScoreReason::EnemyDestroyed,
+            player: event.destroyed_by,
+        });
+    }
+}
+
+pub fn score_defeated_bosses(
+    mut defeated: EventReader<BossDefeated>,
+    mut score_events: EventWriter<ScoreEvent>,
+) {
+    for event in defeated.read() {
+        score_events.send(ScoreEvent {
+            amount: event.score,
+            reason: ScoreReason::BossDefeated,
+            player: event.destroyed_by,
+        });
+    }
+}
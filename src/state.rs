@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GameState {
+    #[default]
+    Loading,
+    MainMenu,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+/// Active while a run is in progress, paused or not. Gameplay and HUD entities
+/// are scoped to it so pausing keeps them around and game over clears them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InGame;
+
+impl ComputedStates for InGame {
+    type SourceStates = GameState;
+
+    fn compute(sources: GameState) -> Option<Self> {
+        match sources {
+            GameState::Playing | GameState::Paused => Some(InGame),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::state::app::StatesPlugin;
+
+    use super::*;
+
+    fn go_to(app: &mut App, state: GameState) -> Option<InGame> {
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(state);
+        app.update();
+        assert_eq!(*app.world().resource::<State<GameState>>().get(), state);
+        app.world()
+            .get_resource::<State<InGame>>()
+            .map(|state| *state.get())
+    }
+
+    #[test]
+    fn in_game_covers_playing_and_paused() {
+        let mut app = App::new();
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .add_computed_state::<InGame>();
+        app.update();
+        assert!(app.world().get_resource::<State<InGame>>().is_none());
+
+        assert_eq!(go_to(&mut app, GameState::MainMenu), None);
+        assert_eq!(go_to(&mut app, GameState::Playing), Some(InGame));
+        assert_eq!(go_to(&mut app, GameState::Paused), Some(InGame));
+        assert_eq!(go_to(&mut app, GameState::Playing), Some(InGame));
+        assert_eq!(go_to(&mut app, GameState::GameOver), None);
+        assert_eq!(go_to(&mut app, GameState::MainMenu), None);
+    }
+}
@@ -0,0 +1,62 @@
+use std::{fs, io, path::Path};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Reads the `what` stored at `path`, `None` if there is none or it can't be read. Problems
+/// other than a missing file are logged.
+pub fn load_ron<T: DeserializeOwned>(path: &Path, what: &str) -> Option<T> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return None,
+        Err(err) => {
+            warn!("could not read {what} from {}: {err}", path.display());
+            return None;
+        }
+    };
+    match ron::from_str(&contents) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            warn!("ignoring corrupt {what} in {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+/// Writes `value` to `path`, creating its directory first. Only files meant for editing
+/// by hand need to be `pretty`.
+pub fn save_ron<T: Serialize>(path: &Path, value: &T, pretty: bool) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let contents = if pretty {
+        ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+    } else {
+        ron::ser::to_string(value)
+    }
+    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    fs::write(path, contents)
+}
+
+/// Saves `saved` and checks it loads back, then that a missing and a corrupt file both
+/// load as `fallback`. Files go to a temporary directory named after `name`.
+#[cfg(test)]
+pub fn assert_persists<T: PartialEq + std::fmt::Debug>(
+    name: &str,
+    saved: T,
+    fallback: T,
+    save: impl Fn(&T, &Path) -> io::Result<()>,
+    load: impl Fn(&Path) -> T,
+) {
+    let dir =
+        std::env::temp_dir().join(format!("spaceship-game-{name}-test-{}", std::process::id()));
+    let path = dir.join("nested").join(format!("{name}.ron"));
+    save(&saved, &path).unwrap();
+    assert_eq!(load(&path), saved);
+
+    assert_eq!(load(&dir.join("missing.ron")), fallback);
+    let corrupt = dir.join("corrupt.ron");
+    fs::write(&corrupt, "(not: [valid").unwrap();
+    assert_eq!(load(&corrupt), fallback);
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,113 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use rand::Rng;
+
+use crate::{
+    asteroids::{pick_size, spawn_falling_asteroid, Asteroid, Velocity},
+    bosses::Boss,
+    GameAssets, GameConfig, GameRng, SizeMix, WaveDefinition,
+};
+
+/// Delay before the first wave of a run.
+const FIRST_WAVE_DELAY: f32 = 1.0;
+
+#[derive(Event, Clone, Copy, Debug)]
+pub enum WaveEvent {
+    Started(u32),
+    Completed(u32),
+}
+
+/// Spawns the asteroids of the current wave and moves on once the field is clear.
+#[derive(Resource)]
+pub struct WaveDirector {
+    pub wave: u32,
+    pub definition: WaveDefinition,
+    /// Asteroids of each size of the current wave that haven't been spawned yet.
+    pub remaining: SizeMix,
+    spawn_timer: Timer,
+    /// Counts down to the next wave while between waves.
+    break_timer: Option<Timer>,
+}
+
+impl Default for WaveDirector {
+    fn default() -> Self {
+        Self {
+            wave: 0,
+            definition: WaveDefinition::default(),
+            remaining: SizeMix::default(),
+            spawn_timer: Timer::from_seconds(0., TimerMode::Repeating),
+            break_timer: Some(Timer::from_seconds(FIRST_WAVE_DELAY, TimerMode::Once)),
+        }
+    }
+}
+
+impl WaveDirector {
+    fn start_wave(&mut self, wave: u32, config: &GameConfig) {
+        let definition = config.waves.wave(wave);
+        self.wave = wave;
+        // Boss waves are just the boss and whatever it summons.
+        self.remaining = if config.waves.is_boss_wave(wave) {
+            SizeMix::default()
+        } else {
+            config.asteroids.wave_counts(definition.scale)
+        };
+        self.spawn_timer = Timer::from_seconds(definition.spawn_interval, TimerMode::Repeating);
+        self.definition = definition;
+        self.break_timer = None;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run_wave_director(
+    mut commands: Commands,
+    mut director: ResMut<WaveDirector>,
+    asteroids_query: Query<(), With<Asteroid>>,
+    boss_query: Query<(), With<Boss>>,
+    win_query: Query<&Window, With<PrimaryWindow>>,
+    game_assets: Res<GameAssets>,
+    config: Res<GameConfig>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+    mut wave_events: EventWriter<WaveEvent>,
+) {
+    let Ok(win) = win_query.get_single() else {
+        return;
+    };
+    if let Some(timer) = director.break_timer.as_mut() {
+        if timer.tick(time.delta()).finished() {
+            let wave = director.wave + 1;
+            director.start_wave(wave, &config);
+            wave_events.send(WaveEvent::Started(wave));
+        }
+    } else if director.remaining.total() > 0 {
+        let spawns = director
+            .spawn_timer
+            .tick(time.delta())
+            .times_finished_this_tick() as usize;
+        for _ in 0..spawns.min(director.remaining.total() as usize) {
+            // Drawn from what is left, so the wave ends up with exactly its counts.
+            let size = pick_size(&director.remaining, rng.as_mut());
+            *director.remaining.weight_mut(size) -= 1;
+            let velocity = Velocity {
+                speed: rng.gen_range(director.definition.min_speed..director.definition.max_speed),
+                direction_x: rng.gen_range(-config.asteroids.max_drift..config.asteroids.max_drift),
+            };
+            spawn_falling_asteroid(
+                &mut commands,
+                &config.asteroids,
+                size,
+                game_assets.asteroid.clone(),
+                win,
+                velocity,
+                rng.as_mut(),
+            );
+        }
+    } else if asteroids_query.is_empty() && boss_query.is_empty() {
+        // Asteroids and bosses spawned this frame only show up in the queries next frame,
+        // which is why spawning and this check never run in the same frame.
+        wave_events.send(WaveEvent::Completed(director.wave));
+        director.break_timer = Some(Timer::from_seconds(
+            config.waves.break_time,
+            TimerMode::Once,
+        ));
+    }
+}
@@ -0,0 +1,263 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    asteroids::Asteroid, duration_from_secs, spawn_bullet, Bullet, Faction, Hostile, WeaponConfig,
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum WeaponPattern {
+    Single,
+    /// `count` bullets fanned over `angle` degrees.
+    Spread {
+        count: usize,
+        angle: f32,
+    },
+    /// `count` bullets one after another, `interval` seconds apart, for one round.
+    Burst {
+        count: usize,
+        interval: f32,
+    },
+    /// A long, fast bolt that pierces everything in its way.
+    Laser {
+        length: f32,
+    },
+    /// Missiles turning towards the nearest target by up to `turn_rate` radians a second.
+    Homing {
+        turn_rate: f32,
+    },
+}
+
+impl WeaponPattern {
+    pub fn color(&self) -> Color {
+        match self {
+            WeaponPattern::Single => Color::WHITE,
+            WeaponPattern::Spread { .. } => Color::srgb(0.4, 1., 0.4),
+            WeaponPattern::Burst { .. } => Color::srgb(1., 0.9, 0.2),
+            WeaponPattern::Laser { .. } => Color::srgb(0.3, 0.9, 1.),
+            WeaponPattern::Homing { .. } => Color::srgb(1., 0.5, 0.2),
+        }
+    }
+}
+
+/// One weapon carried by the player, with its own magazine and timers.
+pub struct Weapon {
+    pub stats: WeaponConfig,
+    pub fire_cooldown: Timer,
+    pub reload_timer: Option<Timer>,
+    pub ammunition: usize,
+    /// Rounds left outside the magazine, `None` when the weapon has no reserve limit.
+    pub reserve: Option<usize>,
+    burst_remaining: usize,
+    burst_timer: Timer,
+}
+
+impl Weapon {
+    pub fn new(stats: &WeaponConfig) -> Self {
+        let cooldown = duration_from_secs(stats.fire_cooldown);
+        let mut fire_cooldown = Timer::new(cooldown, TimerMode::Once);
+        // Ready straight away, the first shot shouldn't wait out a cooldown.
+        fire_cooldown.tick(cooldown);
+        Self {
+            stats: stats.clone(),
+            fire_cooldown,
+            reload_timer: None,
+            ammunition: stats.magazine,
+            reserve: stats.reserve,
+            burst_remaining: 0,
+            burst_timer: Timer::from_seconds(0., TimerMode::Once),
+        }
+    }
+
+    /// Swaps in new stats from a reloaded config, keeping the magazine.
+    pub fn set_stats(&mut self, stats: &WeaponConfig) {
+        self.fire_cooldown
+            .set_duration(duration_from_secs(stats.fire_cooldown));
+        self.ammunition = self.ammunition.min(stats.magazine);
+        self.reserve = match (self.reserve, stats.reserve) {
+            (Some(reserve), Some(max)) => Some(reserve.min(max)),
+            (_, reserve) => reserve,
+        };
+        self.stats = stats.clone();
+    }
+
+    /// Spends one round if the cooldown has elapsed and the magazine isn't empty.
+    pub fn try_fire(&mut self) -> bool {
+        if !self.fire_cooldown.finished() || self.ammunition == 0 || self.reload_timer.is_some() {
+            return false;
+        }
+        self.fire_cooldown.reset();
+        self.ammunition -= 1;
+        if let WeaponPattern::Burst { count, interval } = self.stats.pattern {
+            self.burst_remaining = count.saturating_sub(1);
+            self.burst_timer = Timer::new(duration_from_secs(interval), TimerMode::Once);
+        }
+        true
+    }
+
+    /// Whether the next bullet of a burst is due, consuming it if so.
+    pub fn take_burst_shot(&mut self) -> bool {
+        if self.burst_remaining == 0 || !self.burst_timer.finished() {
+            return false;
+        }
+        self.burst_remaining -= 1;
+        self.burst_timer.reset();
+        true
+    }
+
+    /// Starts reloading a magazine that is missing rounds, returns whether a reload was started.
+    pub fn start_reload(&mut self) -> bool {
+        if self.ammunition >= self.stats.magazine
+            || self.reserve == Some(0)
+            || self.reload_timer.is_some()
+        {
+            return false;
+        }
+        self.reload_timer = Some(Timer::new(
+            duration_from_secs(self.stats.reload_time),
+            TimerMode::Once,
+        ));
+        true
+    }
+
+    /// Advances the timers, `cooldown_delta` lets rapid fire speed up the cooldown alone.
+    pub fn tick(&mut self, delta: Duration, cooldown_delta: Duration) {
+        self.fire_cooldown.tick(cooldown_delta);
+        self.burst_timer.tick(delta);
+        let reloaded = self
+            .reload_timer
+            .as_mut()
+            .is_some_and(|timer| timer.tick(delta).finished());
+        if reloaded {
+            self.reload_timer = None;
+            self.refill();
+        }
+    }
+
+    /// Drops a running reload and the rest of a burst, for switching to another weapon.
+    pub fn holster(&mut self) {
+        self.reload_timer = None;
+        self.burst_remaining = 0;
+    }
+
+    /// Fraction of the running reload that is done, `None` when not reloading.
+    pub fn reload_progress(&self) -> Option<f32> {
+        self.reload_timer.as_ref().map(Timer::fraction)
+    }
+
+    /// Tops the magazine up from the reserve.
+    fn refill(&mut self) {
+        let missing = self.stats.magazine.saturating_sub(self.ammunition);
+        let rounds = self.reserve.map_or(missing, |reserve| reserve.min(missing));
+        self.ammunition += rounds;
+        if let Some(reserve) = &mut self.reserve {
+            *reserve -= rounds;
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Homing {
+    pub turn_rate: f32,
+}
+
+/// Spawns the bullets of one shot. `extra_spread` fans every bullet out into three, for the
+/// spread shot power-up.
+pub fn fire_weapon(
+    commands: &mut Commands,
+    owner: Entity,
+    stats: &WeaponConfig,
+    texture: Handle<Image>,
+    translation: Vec3,
+    bullet_size: f32,
+    extra_spread: Option<f32>,
+) {
+    let mut directions = match &stats.pattern {
+        WeaponPattern::Spread { count, angle } => {
+            let step = if *count > 1 {
+                angle.to_radians() / (*count - 1) as f32
+            } else {
+                0.
+            };
+            let first = -step * (*count as f32 - 1.) / 2.;
+            (0..*count)
+                .map(|n| Vec2::from_angle(first + step * n as f32).rotate(Vec2::Y))
+                .collect()
+        }
+        _ => vec![Vec2::Y],
+    };
+    if let Some(angle) = extra_spread {
+        let angle = angle.to_radians();
+        directions = directions
+            .into_iter()
+            .flat_map(|direction| {
+                [
+                    direction,
+                    Vec2::from_angle(angle).rotate(direction),
+                    Vec2::from_angle(-angle).rotate(direction),
+                ]
+            })
+            .collect();
+    }
+    for direction in directions {
+        let mut bullet = Bullet::new(Faction::Player, direction * stats.speed);
+        bullet.damage = stats.damage;
+        bullet.owner = Some(owner);
+        let size = match stats.pattern {
+            WeaponPattern::Laser { length } => {
+                bullet.piercing = true;
+                Vec2::new(bullet_size, length)
+            }
+            _ => Vec2::splat(bullet_size),
+        };
+        let mut entity = spawn_bullet(commands, bullet, texture.clone(), translation, size);
+        match stats.pattern {
+            WeaponPattern::Laser { .. } => {
+                entity.insert(Sprite {
+                    color: stats.pattern.color(),
+                    custom_size: Some(size),
+                    ..default()
+                });
+            }
+            WeaponPattern::Homing { turn_rate } => {
+                entity.insert(Homing { turn_rate });
+            }
+            _ => {}
+        }
+    }
+}
+
+type HomingTarget = (Or<(With<Asteroid>, Hostile)>, Without<Bullet>);
+
+/// Turns homing missiles towards the closest asteroid, enemy or boss.
+pub fn steer_homing(
+    mut missiles_query: Query<(&mut Bullet, &mut Transform, &Homing)>,
+    targets_query: Query<&Transform, HomingTarget>,
+    time: Res<Time>,
+) {
+    for (mut bullet, mut transform, homing) in missiles_query.iter_mut() {
+        let position = transform.translation.truncate();
+        let target = targets_query
+            .iter()
+            .map(|target| target.translation.truncate())
+            .filter(|target| target.y > position.y)
+            .min_by(|a, b| {
+                a.distance_squared(position)
+                    .total_cmp(&b.distance_squared(position))
+            });
+        let Some(target) = target else {
+            continue;
+        };
+        let Some(wanted) = (target - position).try_normalize() else {
+            continue;
+        };
+        let current = bullet.velocity.normalize_or(Vec2::Y);
+        let angle = current.angle_between(wanted);
+        let max_turn = homing.turn_rate * time.delta_seconds();
+        let turned = Vec2::from_angle(angle.clamp(-max_turn, max_turn)).rotate(current);
+        bullet.velocity = turned * bullet.velocity.length();
+        transform.rotation = Quat::from_rotation_arc_2d(Vec2::Y, turned);
+    }
+}